@@ -31,6 +31,8 @@ BuildRequires:  systemd
 BuildRequires:  sassc
 
 Requires:       dbus
+# pw-dump and pw-cli for the PipeWire volume backend
+Recommends:     pipewire-utils
 %{?systemd_requires}
 
 %description
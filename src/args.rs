@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -16,9 +16,28 @@ pub struct ArgsServer {
 	/// OSD margin from top edge (0.5 would be screen center). Default is 0.85
 	#[arg(long, value_name = "from 0.0 to 1.0")]
 	pub top_margin: Option<String>,
+
+	/// Creates and reads a wob compatible FIFO. Each line is "<value>" or "<value> <style>"
+	#[arg(long, value_name = "FIFO Path")]
+	pub wob_fifo: Option<PathBuf>,
+
+	/// How the OSDs are displayed. log and json print one line per OSD
+	/// instead of opening windows, and don't need a Wayland display
+	#[arg(long, value_enum, default_value_t = Renderer::Gtk)]
+	pub renderer: Renderer,
 }
 
-#[derive(Parser)]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Renderer {
+	/// Layer-shell windows on every monitor
+	Gtk,
+	/// Same lines as `swayosd-client watch`
+	Log,
+	/// Same lines as `swayosd-client watch --json`
+	Json,
+}
+
+#[derive(Parser, Clone)]
 #[command(version, about, long_about = None)]
 #[command(arg_required_else_help(true))]
 pub struct ArgsClient {
@@ -30,6 +49,36 @@ pub struct ArgsClient {
 	#[arg(long, value_name = "Monitor identifier (e.g., HDMI-A-1, DP-1)")]
 	pub monitor: Option<String>,
 
+	#[command(subcommand)]
+	pub command: Option<ClientCommand>,
+
+	/// Runs the flags of a preset from the [client.presets] section of the config.
+	/// Flags passed on the command line take precedence
+	#[arg(long, value_name = "Preset name")]
+	pub preset: Option<String>,
+
+	/// Prints the current value without displaying the OSD.
+	/// Uses --device for the volume and brightness
+	#[arg(
+		long,
+		value_name = "output-volume|input-volume|brightness|caps-lock|num-lock|scroll-lock"
+	)]
+	pub get: Option<String>,
+
+	/// Prints the resulting state of each action (or of --get) as a JSON object per line
+	#[arg(long, global = true, default_value_t = false)]
+	pub json: bool,
+
+	/// Applies the changes without swayosd-server and doesn't display the OSD.
+	/// Used automatically when the server isn't running
+	#[arg(long, default_value_t = false)]
+	pub direct: bool,
+
+	/// Keeps the connection open and reads one command per line from stdin.
+	/// Ex: "output-volume +5", "custom-progress 0.42 text=Copying"
+	#[arg(long, default_value_t = false)]
+	pub listen: bool,
+
 	/// Shows capslock osd. Note: Doesn't toggle CapsLock, just displays the status
 	#[arg(long, default_value_t = false)]
 	pub caps_lock: bool,
@@ -110,7 +159,7 @@ pub struct ArgsClient {
 	/// Icon to display when using custom-message/custom-progress.
 	/// Icon name is from Freedesktop specification
 	/// (https://specifications.freedesktop.org/icon-naming-spec/latest/)
-	#[arg(long, value_name = "Icon name")]
+	#[arg(long, alias = "icon", value_name = "Icon name")]
 	pub custom_icon: Option<String>,
 
 	/// Progress to display (0.0 <-> 1.0)
@@ -122,6 +171,35 @@ pub struct ArgsClient {
 	pub custom_segmented_progress: Option<String>,
 
 	/// Text to display when using custom-progress or custom-segmented-progress
-	#[arg(long, value_name = "Progress text")]
+	#[arg(long, alias = "text", value_name = "Progress text")]
 	pub custom_progress_text: Option<String>,
+
+	/// Tracks the custom-progress or custom-segmented-progress under an ID.
+	/// Later progresses with the same ID update it and keep its text and icon
+	#[arg(long, value_name = "Progress ID")]
+	pub id: Option<String>,
+
+	/// Keeps the progress sent with --id displayed until it's closed with --close
+	#[arg(long, default_value_t = false, requires = "id")]
+	pub persistent: bool,
+
+	/// Hides the progress that was sent with the ID
+	#[arg(long, value_name = "Progress ID")]
+	pub close: Option<String>,
+
+	/// Applies the actions without displaying the OSDs while on
+	#[arg(long, value_name = "on|off|toggle")]
+	pub inhibit: Option<String>,
+
+	/// Kinds of OSDs that are still displayed while inhibited, replacing the configured ones.
+	/// Ex: caps-lock,num-lock
+	#[arg(long, value_name = "Comma separated kinds", requires = "inhibit")]
+	pub inhibit_shown_kinds: Option<String>,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ClientCommand {
+	/// Prints a line every time the server displays an OSD, including the lock key
+	/// changes reported by swayosd-libinput-backend. Needs the D-Bus session bus
+	Watch,
 }
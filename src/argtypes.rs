@@ -12,6 +12,9 @@ pub enum ArgTypes {
 	MonitorName = (i32::MIN + 5) as isize,
 	CustomProgressText = (i32::MIN + 6) as isize,
 	MinBrightness = (i32::MIN + 7) as isize,
+	ProgressId = (i32::MIN + 8) as isize,
+	Persistent = (i32::MIN + 9) as isize,
+	InhibitShownKinds = (i32::MIN + 10) as isize,
 	// Other
 	CapsLock = 1,
 	SinkVolumeRaise = 2,
@@ -30,6 +33,8 @@ pub enum ArgTypes {
 	CustomProgress = 15,
 	CustomSegmentedProgress = 16,
 	KbdBacklight = 17,
+	CloseProgress = 18,
+	Inhibit = 19,
 }
 
 impl fmt::Display for ArgTypes {
@@ -60,6 +65,11 @@ impl fmt::Display for ArgTypes {
 			ArgTypes::CustomProgressText => "CUSTOM-PROGRESS-TEXT",
 			ArgTypes::MinBrightness => "MIN-BRIGHTNESS",
 			ArgTypes::KbdBacklight => "KBD-BACKLIGHT",
+			ArgTypes::ProgressId => "PROGRESS-ID",
+			ArgTypes::Persistent => "PERSISTENT",
+			ArgTypes::CloseProgress => "CLOSE-PROGRESS",
+			ArgTypes::InhibitShownKinds => "INHIBIT-SHOWN-KINDS",
+			ArgTypes::Inhibit => "INHIBIT",
 		};
 		write!(f, "{}", string)
 	}
@@ -95,8 +105,167 @@ impl str::FromStr for ArgTypes {
 			"CUSTOM-PROGRESS-TEXT" => ArgTypes::CustomProgressText,
 			"MIN-BRIGHTNESS" => ArgTypes::MinBrightness,
 			"KBD-BACKLIGHT" => ArgTypes::KbdBacklight,
+			"PROGRESS-ID" => ArgTypes::ProgressId,
+			"PERSISTENT" => ArgTypes::Persistent,
+			"CLOSE-PROGRESS" => ArgTypes::CloseProgress,
+			"INHIBIT-SHOWN-KINDS" => ArgTypes::InhibitShownKinds,
+			"INHIBIT" => ArgTypes::Inhibit,
 			other_type => return Err(other_type.to_owned()),
 		};
 		Ok(result)
 	}
 }
+
+pub const ICON_NAME_DEFAULT: &str = "text-x-generic";
+
+/// The modifier actions of a single request, applied to every action in that request
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionContext {
+	pub device_name: Option<String>,
+	pub monitor_name: Option<String>,
+	pub icon_name: Option<String>,
+	pub progress_text: Option<String>,
+	pub player: Option<String>,
+	pub max_volume: Option<u8>,
+	pub min_brightness: Option<u32>,
+	/// Tracks a custom progress under this ID until it's closed
+	pub progress_id: Option<String>,
+	/// Keeps the tracked progress displayed until it's closed
+	pub persistent: bool,
+	/// Replaces the kinds of OSDs that are still displayed while inhibited
+	pub inhibit_shown_kinds: Option<Vec<String>>,
+}
+
+impl ActionContext {
+	/// Stores the value of a modifier action. Returns false if the action isn't a modifier
+	pub fn set_modifier(&mut self, arg_type: &ArgTypes, value: &Option<String>) -> bool {
+		let value = value.clone();
+		match arg_type {
+			ArgTypes::DeviceName => self.device_name = value,
+			ArgTypes::MonitorName => self.monitor_name = value.or(self.monitor_name.take()),
+			ArgTypes::CustomIcon => {
+				self.icon_name = Some(value.unwrap_or(ICON_NAME_DEFAULT.to_owned()))
+			}
+			ArgTypes::CustomProgressText => self.progress_text = value,
+			ArgTypes::Player => self.player = value,
+			ArgTypes::MaxVolume => self.max_volume = value.and_then(|v| v.parse().ok()),
+			ArgTypes::MinBrightness => self.min_brightness = value.and_then(|v| v.parse().ok()),
+			ArgTypes::ProgressId => self.progress_id = value,
+			ArgTypes::Persistent => self.persistent = value.is_none_or(|v| v != "false"),
+			ArgTypes::InhibitShownKinds => {
+				self.inhibit_shown_kinds = Some(split_kinds(value.as_deref().unwrap_or_default()))
+			}
+			_ => return false,
+		}
+		true
+	}
+
+	/// The modifier actions that recreate this context
+	pub fn to_modifiers(&self) -> Vec<(ArgTypes, Option<String>)> {
+		let modifiers = [
+			(ArgTypes::DeviceName, self.device_name.clone()),
+			(ArgTypes::MonitorName, self.monitor_name.clone()),
+			(ArgTypes::CustomIcon, self.icon_name.clone()),
+			(ArgTypes::CustomProgressText, self.progress_text.clone()),
+			(ArgTypes::Player, self.player.clone()),
+			(ArgTypes::MaxVolume, self.max_volume.map(|v| v.to_string())),
+			(
+				ArgTypes::MinBrightness,
+				self.min_brightness.map(|v| v.to_string()),
+			),
+			(ArgTypes::ProgressId, self.progress_id.clone()),
+			(
+				ArgTypes::Persistent,
+				self.persistent.then(|| "true".to_owned()),
+			),
+			(
+				ArgTypes::InhibitShownKinds,
+				self.inhibit_shown_kinds
+					.as_ref()
+					.map(|kinds| kinds.join(",")),
+			),
+		];
+		modifiers
+			.into_iter()
+			.filter(|(_, value)| value.is_some())
+			.collect()
+	}
+}
+
+/// Splits a comma separated list of OSD kinds. Ex: "caps-lock,num-lock"
+pub fn split_kinds(kinds: &str) -> Vec<String> {
+	kinds
+		.split(',')
+		.map(str::trim)
+		.filter(|kind| !kind.is_empty())
+		.map(str::to_owned)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn from_modifiers(modifiers: &[(ArgTypes, Option<String>)]) -> ActionContext {
+		let mut context = ActionContext::default();
+		for (arg_type, value) in modifiers {
+			assert!(context.set_modifier(arg_type, value));
+		}
+		context
+	}
+
+	#[test]
+	fn to_modifiers_round_trips_every_field() {
+		let context = ActionContext {
+			device_name: Some("alsa_output.pci-0000_00_1f.3.analog-stereo".to_owned()),
+			monitor_name: Some("eDP-1".to_owned()),
+			icon_name: Some("folder-download-symbolic".to_owned()),
+			progress_text: Some("Copying".to_owned()),
+			player: Some("spotify".to_owned()),
+			max_volume: Some(150),
+			min_brightness: Some(10),
+			progress_id: Some("copy".to_owned()),
+			persistent: true,
+			inhibit_shown_kinds: Some(vec!["caps-lock".to_owned(), "num-lock".to_owned()]),
+		};
+		assert_eq!(from_modifiers(&context.to_modifiers()), context);
+	}
+
+	#[test]
+	fn to_modifiers_skips_unset_fields() {
+		assert!(ActionContext::default().to_modifiers().is_empty());
+
+		let context = ActionContext {
+			monitor_name: Some("DP-1".to_owned()),
+			inhibit_shown_kinds: Some(Vec::new()),
+			..Default::default()
+		};
+		let modifiers = context.to_modifiers();
+		assert_eq!(
+			modifiers,
+			[
+				(ArgTypes::MonitorName, Some("DP-1".to_owned())),
+				(ArgTypes::InhibitShownKinds, Some(String::new())),
+			]
+		);
+		assert_eq!(from_modifiers(&modifiers), context);
+	}
+
+	#[test]
+	fn set_modifier_ignores_actions() {
+		let mut context = ActionContext::default();
+		assert!(!context.set_modifier(&ArgTypes::SinkVolumeRaise, &Some("5".to_owned())));
+		assert_eq!(context, ActionContext::default());
+	}
+
+	#[test]
+	fn split_kinds_trims_and_skips_empty_kinds() {
+		assert_eq!(split_kinds("caps-lock"), ["caps-lock"]);
+		assert_eq!(
+			split_kinds(" caps-lock, num-lock ,,sink-volume,"),
+			["caps-lock", "num-lock", "sink-volume"]
+		);
+		assert!(split_kinds("").is_empty());
+		assert!(split_kinds(" , ").is_empty());
+	}
+}
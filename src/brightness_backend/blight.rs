@@ -25,6 +25,14 @@ impl BrightnessBackend for Blight {
 		self.device.max()
 	}
 
+	fn get_backend_name(&self) -> &'static str {
+		"blight"
+	}
+
+	fn get_device_name(&mut self) -> String {
+		self.device.name().to_owned()
+	}
+
 	fn lower(&mut self, by: u32, min: u32) -> anyhow::Result<()> {
 		let val = self.device.calculate_change(by, Direction::Dec).max(min);
 		Ok(self.device.write_value(val)?)
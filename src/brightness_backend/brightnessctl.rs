@@ -126,6 +126,14 @@ impl VirtualDevice {
 		Ok(())
 	}
 
+	fn get_name(&self) -> String {
+		// The first field of the machine readable info is the resolved device name
+		match self.run::<String, _>("--machine-readable") {
+			Ok(info) => info.split(',').next().unwrap_or_default().to_owned(),
+			Err(_) => self.name.clone().unwrap_or_default(),
+		}
+	}
+
 	pub fn get_percent(&mut self) -> u32 {
 		let curr = self.get_current();
 		let max = self.get_max();
@@ -150,6 +158,14 @@ impl BrightnessBackend for BrightnessCtl {
 		self.device.get_max()
 	}
 
+	fn get_backend_name(&self) -> &'static str {
+		"brightnessctl"
+	}
+
+	fn get_device_name(&mut self) -> String {
+		self.device.get_name()
+	}
+
 	fn lower(&mut self, by: u32, min: u32) -> anyhow::Result<()> {
 		let max = self.device.get_max();
 		let curr = self.device.get_current();
@@ -88,6 +88,15 @@ impl DdcDevice {
 		self.max
 	}
 
+	fn get_name(&self) -> String {
+		let display = self.display.borrow();
+		display
+			.info
+			.model_name
+			.clone()
+			.unwrap_or_else(|| display.info.id.clone())
+	}
+
 	fn get_percent(&mut self) -> u32 {
 		let cur = self.get_current();
 		let max = self.get_max();
@@ -142,6 +151,14 @@ impl BrightnessBackend for Ddcci {
 		self.device.get_max()
 	}
 
+	fn get_backend_name(&self) -> &'static str {
+		"ddcci"
+	}
+
+	fn get_device_name(&mut self) -> String {
+		self.device.get_name()
+	}
+
 	fn lower(&mut self, by: u32, min: u32) -> anyhow::Result<()> {
 		let max = self.device.get_max();
 		let cur = self.device.get_current();
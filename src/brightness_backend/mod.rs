@@ -24,6 +24,8 @@ pub trait BrightnessBackendConstructor: BrightnessBackend + Sized + 'static {
 pub trait BrightnessBackend {
 	fn get_current(&mut self) -> u32;
 	fn get_max(&mut self) -> u32;
+	fn get_backend_name(&self) -> &'static str;
+	fn get_device_name(&mut self) -> String;
 
 	fn lower(&mut self, by: u32, min: u32) -> anyhow::Result<()>;
 	fn raise(&mut self, by: u32, min: u32) -> anyhow::Result<()>;
@@ -32,14 +34,14 @@ pub trait BrightnessBackend {
 
 #[allow(dead_code)]
 pub fn get_preferred_backend(device_name: Option<String>) -> BrightnessBackendResult {
-	println!("Trying BrightnessCtl Backend...");
+	eprintln!("Trying BrightnessCtl Backend...");
 	BrightnessCtl::try_new_boxed(device_name.clone())
 		.or_else(|_| {
-			println!("Trying Blight Backend...");
+			eprintln!("Trying Blight Backend...");
 			Blight::try_new_boxed(device_name.clone())
 		})
 		.or_else(|_| {
-			println!("Trying DDC/CI Backend...");
+			eprintln!("Trying DDC/CI Backend...");
 			Ddcci::try_new_boxed(device_name)
 		})
 }
@@ -0,0 +1,322 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::Serialize;
+use zbus::{blocking::Connection, zvariant::Value};
+
+use crate::argtypes::{ActionContext, ArgTypes};
+use crate::brightness_backend;
+use crate::config::user::{AudioBackend, ServerConfig};
+use crate::error::ActionError;
+use crate::global_utils::{progress_parser, segmented_progress_parser};
+use crate::playerctl::{Playerctl, PlayerctlAction, PlayerctlDeviceRaw};
+use crate::state::{
+	BrightnessState, KeyLockState, PlayerState, ProgressState, SegmentedProgressState, VolumeState,
+};
+use crate::utils::{
+	brightness_state, change_brightness, change_device_volume, get_device_volume,
+	get_key_lock_state, volume_state, BrightnessChangeType, KeysLocks, VolumeChangeType,
+	VolumeDeviceType, PRIV_MAX_VOLUME_DEFAULT, PRIV_MIN_BRIGHTNESS_DEFAULT,
+};
+use crate::volume_backend::get_preferred_backend;
+
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Applies the actions in-process when swayosd-server isn't running.
+/// Nothing is drawn, but the result can be sent as a desktop notification instead.
+/// The defaults are read from the [server] section of the config
+pub struct Direct {
+	server_config: Arc<ServerConfig>,
+	notify: bool,
+}
+
+impl Direct {
+	pub fn new(server_config: Arc<ServerConfig>, notify: bool) -> Self {
+		Self {
+			server_config,
+			notify,
+		}
+	}
+
+	/// Returns the resulting state as JSON if the action produced one
+	pub fn send_action(
+		&self,
+		context: &ActionContext,
+		arg_type: ArgTypes,
+		data: Option<String>,
+	) -> Result<Option<serde_json::Value>, ActionError> {
+		let state = match arg_type {
+			ArgTypes::SinkVolumeRaise => to_json(&self.volume(
+				context,
+				VolumeDeviceType::Sink,
+				VolumeChangeType::Raise,
+				data,
+			)?)?,
+			ArgTypes::SinkVolumeLower => to_json(&self.volume(
+				context,
+				VolumeDeviceType::Sink,
+				VolumeChangeType::Lower,
+				data,
+			)?)?,
+			ArgTypes::SinkVolumeMuteToggle => to_json(&self.volume(
+				context,
+				VolumeDeviceType::Sink,
+				VolumeChangeType::MuteToggle,
+				None,
+			)?)?,
+			ArgTypes::SourceVolumeRaise => to_json(&self.volume(
+				context,
+				VolumeDeviceType::Source,
+				VolumeChangeType::Raise,
+				data,
+			)?)?,
+			ArgTypes::SourceVolumeLower => to_json(&self.volume(
+				context,
+				VolumeDeviceType::Source,
+				VolumeChangeType::Lower,
+				data,
+			)?)?,
+			ArgTypes::SourceVolumeMuteToggle => to_json(&self.volume(
+				context,
+				VolumeDeviceType::Source,
+				VolumeChangeType::MuteToggle,
+				None,
+			)?)?,
+			ArgTypes::BrightnessRaise => {
+				to_json(&self.brightness(context, BrightnessChangeType::Raise, data)?)?
+			}
+			ArgTypes::BrightnessLower => {
+				to_json(&self.brightness(context, BrightnessChangeType::Lower, data)?)?
+			}
+			ArgTypes::BrightnessSet => {
+				to_json(&self.brightness(context, BrightnessChangeType::Set, data)?)?
+			}
+			ArgTypes::CapsLock | ArgTypes::NumLock | ArgTypes::ScrollLock => {
+				let key = match arg_type {
+					ArgTypes::CapsLock => "caps-lock",
+					ArgTypes::NumLock => "num-lock",
+					_ => "scroll-lock",
+				};
+				let state = match data.as_deref().map(str::parse::<i32>) {
+					Some(Ok(value)) if (0..=1).contains(&value) => value == 1,
+					_ => get_key_lock_state(lock_key(key)?, data),
+				};
+				to_json(&self.lock_state(key, state))?
+			}
+			ArgTypes::Playerctl => to_json(&self.player(context, data)?)?,
+			ArgTypes::CustomMessage => {
+				let message = data.unwrap_or_default();
+				self.notify(&message, context.icon_name.as_deref(), None);
+				return Ok(None);
+			}
+			ArgTypes::CustomProgress => {
+				let data = data.unwrap_or_default();
+				let fraction = progress_parser(&data).map_err(ActionError::InvalidValue)?;
+				let fraction = fraction.clamp(0.0, 1.0);
+				self.notify(
+					context.progress_text.as_deref().unwrap_or_default(),
+					context.icon_name.as_deref(),
+					Some(fraction * 100.0),
+				);
+				to_json(&ProgressState { fraction })?
+			}
+			ArgTypes::CustomSegmentedProgress => {
+				let (value, n_segments) = segmented_progress_parser(&data.unwrap_or_default())
+					.map_err(ActionError::InvalidValue)?;
+				let value = value.min(n_segments);
+				self.notify(
+					context.progress_text.as_deref().unwrap_or_default(),
+					context.icon_name.as_deref(),
+					Some(value as f64 / n_segments.max(1) as f64 * 100.0),
+				);
+				to_json(&SegmentedProgressState { value, n_segments })?
+			}
+			arg_type => {
+				return Err(ActionError::Failed(format!(
+					"{} needs swayosd-server",
+					arg_type
+				)));
+			}
+		};
+		Ok(Some(state))
+	}
+
+	/// Device kind is one of sink|source
+	pub fn get_volume(&self, device_kind: &str, device: &str) -> Result<VolumeState, ActionError> {
+		let device_type = match device_kind {
+			"source" => VolumeDeviceType::Source,
+			_ => VolumeDeviceType::Sink,
+		};
+		let mut backend = get_preferred_backend(device_type, self.audio_backend())?;
+		let device = (!device.is_empty()).then_some(device);
+		let device = get_device_volume(backend.as_mut(), device)?;
+		Ok(volume_state(&device, self.max_volume(None)))
+	}
+
+	pub fn get_brightness(&self, device: &str) -> Result<BrightnessState, ActionError> {
+		let device = (!device.is_empty()).then(|| device.to_owned());
+		match brightness_backend::get_preferred_backend(device) {
+			Ok(mut backend) => Ok(brightness_state(backend.as_mut())),
+			Err(error) => Err(ActionError::BrightnessBackendUnavailable(error.to_string())),
+		}
+	}
+
+	/// Key is one of caps-lock|num-lock|scroll-lock
+	pub fn get_lock_state(&self, key: &str) -> Result<KeyLockState, ActionError> {
+		Ok(KeyLockState {
+			key: key.to_owned(),
+			state: get_key_lock_state(lock_key(key)?, None),
+		})
+	}
+
+	fn volume(
+		&self,
+		context: &ActionContext,
+		device_type: VolumeDeviceType,
+		change_type: VolumeChangeType,
+		step: Option<String>,
+	) -> Result<VolumeState, ActionError> {
+		let max_volume = self.max_volume(context.max_volume);
+		let mut backend = get_preferred_backend(device_type, self.audio_backend())?;
+		let device = change_device_volume(
+			backend.as_mut(),
+			change_type,
+			step,
+			context.device_name.as_deref(),
+			max_volume,
+		)?;
+		let state = volume_state(&device, max_volume);
+		let summary = match state.muted {
+			true => format!("{} (muted)", state.description),
+			false => state.description.clone(),
+		};
+		self.notify(&summary, None, Some(state.volume));
+		Ok(state)
+	}
+
+	fn brightness(
+		&self,
+		context: &ActionContext,
+		change_type: BrightnessChangeType,
+		value: Option<String>,
+	) -> Result<BrightnessState, ActionError> {
+		let min_brightness = context
+			.min_brightness
+			.or(self.server_config.min_brightness)
+			.unwrap_or(PRIV_MIN_BRIGHTNESS_DEFAULT);
+		let mut backend = change_brightness(
+			change_type,
+			value,
+			context.device_name.clone(),
+			min_brightness,
+		)?;
+		let state = brightness_state(backend.as_mut());
+		self.notify(
+			"Brightness",
+			Some("display-brightness-symbolic"),
+			Some(state.percent),
+		);
+		Ok(state)
+	}
+
+	fn lock_state(&self, key: &str, state: bool) -> KeyLockState {
+		let on_off = if state { "On" } else { "Off" };
+		let name = match key {
+			"caps-lock" => "Caps Lock",
+			"num-lock" => "Num Lock",
+			_ => "Scroll Lock",
+		};
+		self.notify(&format!("{} {}", name, on_off), None, None);
+		KeyLockState {
+			key: key.to_owned(),
+			state,
+		}
+	}
+
+	fn player(
+		&self,
+		context: &ActionContext,
+		action: Option<String>,
+	) -> Result<PlayerState, ActionError> {
+		let action = PlayerctlAction::from(&action.unwrap_or_default())
+			.map_err(ActionError::InvalidValue)?;
+		let player = PlayerctlDeviceRaw::from(context.player.clone().unwrap_or_default())
+			.unwrap_or(PlayerctlDeviceRaw::None);
+		let mut player = Playerctl::new(action, player, self.server_config.clone())
+			.map_err(|error| ActionError::NoPlayer(error.to_string()))?;
+		if let Err(error) = player.run() {
+			return Err(ActionError::Failed(format!(
+				"couldn't run player change: \"{:?}\"!",
+				error
+			)));
+		}
+		let state = PlayerState {
+			player: player.player_name.unwrap_or_default(),
+			status: player.status.unwrap_or_default(),
+			icon: player.icon.unwrap_or_default(),
+			label: player.label.unwrap_or_default(),
+		};
+		self.notify(&state.label, Some(&state.icon), None);
+		Ok(state)
+	}
+
+	fn max_volume(&self, max_volume: Option<u8>) -> u8 {
+		max_volume
+			.or(self.server_config.max_volume)
+			.unwrap_or(PRIV_MAX_VOLUME_DEFAULT)
+	}
+
+	fn audio_backend(&self) -> AudioBackend {
+		self.server_config.audio_backend.unwrap_or_default()
+	}
+
+	/// Sends a synchronous notification that replaces the previous one.
+	/// The value is displayed as a progress bar by most notification daemons
+	fn notify(&self, summary: &str, icon: Option<&str>, value: Option<f64>) {
+		if !self.notify {
+			return;
+		}
+		let mut hints: HashMap<&str, Value<'_>> = HashMap::new();
+		hints.insert("x-canonical-private-synchronous", Value::from("swayosd"));
+		if let Some(value) = value {
+			hints.insert("value", Value::from(value.round() as i32));
+		}
+		let result = Connection::session().and_then(|connection| {
+			connection.call_method(
+				Some(NOTIFICATIONS_NAME),
+				NOTIFICATIONS_PATH,
+				Some(NOTIFICATIONS_NAME),
+				"Notify",
+				&(
+					"SwayOSD",
+					0_u32,
+					icon.unwrap_or_default(),
+					summary,
+					"",
+					Vec::<&str>::new(),
+					hints,
+					-1_i32,
+				),
+			)
+		});
+		if let Err(error) = result {
+			eprintln!("Could not send the notification: {}", error);
+		}
+	}
+}
+
+fn lock_key(key: &str) -> Result<KeysLocks, ActionError> {
+	match key {
+		"caps-lock" => Ok(KeysLocks::CapsLock),
+		"num-lock" => Ok(KeysLocks::NumLock),
+		"scroll-lock" => Ok(KeysLocks::ScrollLock),
+		key => Err(ActionError::InvalidValue(format!(
+			"Unknown lock key: \"{}\"",
+			key
+		))),
+	}
+}
+
+fn to_json<T: Serialize>(state: &T) -> Result<serde_json::Value, ActionError> {
+	serde_json::to_value(state).map_err(|error| ActionError::Failed(error.to_string()))
+}
@@ -4,17 +4,52 @@ mod args;
 mod argtypes;
 #[path = "../config.rs"]
 mod config;
+#[path = "../error.rs"]
+mod error;
 #[path = "../global_utils.rs"]
 mod global_utils;
+#[path = "../ipc.rs"]
+mod ipc;
+#[path = "../state.rs"]
+mod state;
 
 #[path = "../brightness_backend/mod.rs"]
 mod brightness_backend;
 
-use clap::Parser;
+#[path = "../mpris-backend/mod.rs"]
+mod playerctl;
+
+#[path = "../volume_backend/mod.rs"]
+mod volume_backend;
+
+// Only the volume, brightness and lock key helpers are used by the direct mode
+#[allow(dead_code)]
+#[path = "../server/utils.rs"]
+mod utils;
+
+mod direct;
+
+use std::{
+	io::{BufRead, BufReader, Write},
+	os::unix::net::UnixStream,
+	sync::Arc,
+};
+
+use clap::{Command, CommandFactory, Parser};
+use evdev_rs::enums::EV_KEY;
+use serde::{de::DeserializeOwned, Serialize};
 use zbus::{blocking::Connection, proxy};
 
-use crate::args::ArgsClient;
-use crate::argtypes::ArgTypes;
+use crate::args::{ArgsClient, ClientCommand};
+use crate::argtypes::{ActionContext, ArgTypes};
+use crate::config::user::{ClientConfig, ClientPreset, ServerConfig};
+use crate::direct::Direct;
+use crate::error::ActionError;
+use crate::ipc::{socket_path, SocketReply, SocketRequest};
+use crate::state::{
+	BrightnessState, InhibitState, KeyLockState, OsdEvent, PlayerState, ProgressState,
+	SegmentedProgressState, VolumeState,
+};
 
 #[proxy(
 	interface = "org.erikreider.swayosd",
@@ -22,41 +57,582 @@ use crate::argtypes::ArgTypes;
 	default_path = "/org/erikreider/swayosd"
 )]
 trait Server {
-	async fn handle_action(&self, arg_type: String, data: String) -> zbus::Result<bool>;
+	async fn handle_action(&self, arg_type: String, data: String) -> Result<bool, ActionError>;
+
+	async fn set_sink_volume(
+		&self,
+		device: &str,
+		mode: &str,
+		step: i32,
+		max: i32,
+		monitor: &str,
+	) -> Result<VolumeState, ActionError>;
+
+	async fn set_source_volume(
+		&self,
+		device: &str,
+		mode: &str,
+		step: i32,
+		max: i32,
+		monitor: &str,
+	) -> Result<VolumeState, ActionError>;
+
+	async fn set_brightness(
+		&self,
+		device: &str,
+		mode: &str,
+		value: i32,
+		min: i32,
+		monitor: &str,
+	) -> Result<BrightnessState, ActionError>;
+
+	async fn show_key_lock(
+		&self,
+		key: &str,
+		state: i32,
+		led: &str,
+		monitor: &str,
+	) -> Result<bool, ActionError>;
+
+	async fn control_player(
+		&self,
+		action: &str,
+		player: &str,
+		monitor: &str,
+	) -> Result<PlayerState, ActionError>;
+
+	async fn show_custom_message(
+		&self,
+		message: &str,
+		icon: &str,
+		monitor: &str,
+	) -> Result<(), ActionError>;
+
+	async fn show_custom_progress(
+		&self,
+		fraction: f64,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+	) -> Result<f64, ActionError>;
+
+	async fn show_tracked_progress(
+		&self,
+		id: &str,
+		fraction: f64,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+		persistent: bool,
+	) -> Result<f64, ActionError>;
+
+	#[allow(clippy::too_many_arguments)]
+	async fn show_tracked_segmented_progress(
+		&self,
+		id: &str,
+		value: u32,
+		n_segments: u32,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+		persistent: bool,
+	) -> Result<(u32, u32), ActionError>;
+
+	async fn close_progress(&self, id: &str) -> Result<(), ActionError>;
+
+	async fn inhibit(&self, mode: &str) -> Result<InhibitState, ActionError>;
+
+	#[zbus(property)]
+	fn set_inhibit_shown_kinds(&self, kinds: &[String]) -> zbus::Result<()>;
+
+	async fn get_volume(&self, device_kind: &str, device: &str)
+		-> Result<VolumeState, ActionError>;
+
+	async fn get_brightness(&self, device: &str) -> Result<BrightnessState, ActionError>;
+
+	async fn get_lock_state(&self, key: &str) -> Result<bool, ActionError>;
+
+	async fn show_custom_segmented_progress(
+		&self,
+		value: u32,
+		n_segments: u32,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+	) -> Result<(u32, u32), ActionError>;
+
+	#[zbus(signal)]
+	#[allow(clippy::too_many_arguments)]
+	fn osd_shown(
+		&self,
+		kind: &str,
+		value: f64,
+		max: f64,
+		muted: bool,
+		label: &str,
+		icon: &str,
+		monitor: &str,
+		device: &str,
+	) -> zbus::Result<()>;
+}
+
+#[proxy(
+	interface = "org.erikreider.swayosd",
+	default_service = "org.erikreider.swayosd",
+	default_path = "/org/erikreider/swayosd"
+)]
+trait Backend {
+	#[zbus(signal)]
+	fn key_pressed(
+		&self,
+		key_code: u16,
+		state: i32,
+		seat: &str,
+		device_path: &str,
+	) -> zbus::Result<()>;
+}
+
+/// The connection to swayosd-server
+enum Server {
+	DBus(ServerProxyBlocking<'static>),
+	/// Used when there's no session bus
+	Socket(UnixStream),
+	/// Used when the server isn't running or with --direct
+	Direct(Direct),
+}
+
+impl Server {
+	/// Sends a single action together with the modifiers of the same invocation.
+	/// Returns the resulting state as JSON if the action produced one
+	fn send_action(
+		&self,
+		context: &ActionContext,
+		arg_type: ArgTypes,
+		data: Option<String>,
+	) -> Result<Option<serde_json::Value>, ActionError> {
+		match self {
+			Server::DBus(proxy) => send_dbus_action(proxy, context, arg_type, data),
+			Server::Socket(stream) => {
+				let mut actions = context.to_modifiers();
+				actions.push((arg_type, data));
+				let actions = actions
+					.into_iter()
+					.map(|(arg_type, data)| (arg_type.to_string(), data))
+					.collect();
+				socket_request(stream, SocketRequest::Actions(actions))
+			}
+			Server::Direct(direct) => direct.send_action(context, arg_type, data),
+		}
+	}
+
+	/// Device kind is one of sink|source
+	fn get_volume(&self, device_kind: &str, device: &str) -> Result<VolumeState, ActionError> {
+		match self {
+			Server::DBus(proxy) => proxy.get_volume(device_kind, device),
+			Server::Socket(stream) => {
+				let value = match device_kind {
+					"source" => "input-volume",
+					_ => "output-volume",
+				};
+				socket_get(stream, value, device)
+			}
+			Server::Direct(direct) => direct.get_volume(device_kind, device),
+		}
+	}
+
+	fn get_brightness(&self, device: &str) -> Result<BrightnessState, ActionError> {
+		match self {
+			Server::DBus(proxy) => proxy.get_brightness(device),
+			Server::Socket(stream) => socket_get(stream, "brightness", device),
+			Server::Direct(direct) => direct.get_brightness(device),
+		}
+	}
+
+	/// Key is one of caps-lock|num-lock|scroll-lock
+	fn get_lock_state(&self, key: &str) -> Result<KeyLockState, ActionError> {
+		match self {
+			Server::DBus(proxy) => Ok(KeyLockState {
+				key: key.to_owned(),
+				state: proxy.get_lock_state(key)?,
+			}),
+			Server::Socket(stream) => socket_get(stream, key, ""),
+			Server::Direct(direct) => direct.get_lock_state(key),
+		}
+	}
 }
 
 fn get_proxy() -> zbus::Result<ServerProxyBlocking<'static>> {
 	let connection = Connection::session()?;
-	ServerProxyBlocking::new(&connection)
+	let proxy = ServerProxyBlocking::new(&connection)?;
+	// Make sure that the server is running
+	proxy.0.introspect()?;
+	Ok(proxy)
+}
+
+/// Falls back to the unix socket when the server can't be reached through the session bus,
+/// and to the direct mode when the server isn't running
+fn connect(args: &ArgsClient, config: &ClientConfig, server_config: ServerConfig) -> Server {
+	let direct = Direct::new(
+		Arc::new(server_config),
+		config.direct_notifications.unwrap_or(false),
+	);
+	if args.direct {
+		return Server::Direct(direct);
+	}
+
+	let dbus_error = match get_proxy() {
+		Ok(proxy) => return Server::DBus(proxy),
+		Err(error) => error,
+	};
+	match UnixStream::connect(socket_path()) {
+		Ok(stream) => Server::Socket(stream),
+		Err(socket_error) => {
+			eprintln!(
+				"Could not connect to SwayOSD Server with error: {}",
+				dbus_error
+			);
+			eprintln!(
+				"Could not connect to {}: {}",
+				socket_path().display(),
+				socket_error
+			);
+			if !config.direct_fallback.unwrap_or(true) {
+				std::process::exit(1);
+			}
+			eprintln!("Applying the changes without swayosd-server");
+			Server::Direct(direct)
+		}
+	}
 }
 
 fn main() {
 	let args = args::ArgsClient::parse();
 
 	// Parse Config
-	let _client_config = config::user::read_user_config(args.config.as_deref())
-		.expect("Failed to parse config file")
-		.client;
+	let user_config = config::user::read_user_config(args.config.as_deref())
+		.expect("Failed to parse config file");
+	let client_config = user_config.client;
 
-	// Make sure that the server is running
-	let proxy = match get_proxy() {
-		Ok(proxy) => match proxy.0.introspect() {
-			Ok(_) => proxy,
-			Err(err) => {
-				eprintln!("Could not connect to SwayOSD Server with error: {}", err);
+	let server = connect(&args, &client_config, user_config.server);
+
+	if args.listen {
+		listen(&args, &server, &client_config);
+		return;
+	}
+
+	if let Some(ClientCommand::Watch) = args.command {
+		if let Err(error) = watch(&args, &server) {
+			eprintln!("Could not watch the OSDs: {}", error);
+			std::process::exit(error.exit_code());
+		}
+		return;
+	}
+
+	if let Err(error) = run(&args, &server, &client_config) {
+		match args.get.as_deref() {
+			Some(value) => eprintln!("Could not get {}: {}", value, error),
+			None => eprintln!("Could not activate action: {}", error),
+		}
+		std::process::exit(error.exit_code());
+	}
+}
+
+/// Writes the request as a line of JSON and reads the reply line
+fn socket_request(
+	mut stream: &UnixStream,
+	request: SocketRequest,
+) -> Result<Option<serde_json::Value>, ActionError> {
+	let socket_error =
+		|error: std::io::Error| ActionError::Failed(format!("Socket error: {}", error));
+	let request =
+		serde_json::to_string(&request).map_err(|error| ActionError::Failed(error.to_string()))?;
+	stream
+		.write_all(format!("{}\n", request).as_bytes())
+		.map_err(socket_error)?;
+	let mut reply = String::new();
+	BufReader::new(stream)
+		.read_line(&mut reply)
+		.map_err(socket_error)?;
+	match serde_json::from_str::<SocketReply>(&reply) {
+		Ok(reply) => reply.into(),
+		Err(error) => Err(ActionError::Failed(format!("Invalid reply: {}", error))),
+	}
+}
+
+fn socket_get<T: DeserializeOwned>(
+	stream: &UnixStream,
+	value: &str,
+	device: &str,
+) -> Result<T, ActionError> {
+	let request = SocketRequest::Get {
+		value: value.to_owned(),
+		device: (!device.is_empty()).then(|| device.to_owned()),
+	};
+	match socket_request(stream, request)? {
+		Some(state) => serde_json::from_value(state)
+			.map_err(|error| ActionError::Failed(format!("Invalid reply: {}", error))),
+		None => Err(ActionError::Failed(
+			"The server didn't reply with a state".to_owned(),
+		)),
+	}
+}
+
+fn run(args: &ArgsClient, server: &Server, config: &ClientConfig) -> Result<(), ActionError> {
+	let preset_args;
+	let args = match args.preset.as_deref() {
+		Some(name) => {
+			let preset = config
+				.presets
+				.as_ref()
+				.and_then(|presets| presets.get(name))
+				.ok_or_else(|| {
+					ActionError::InvalidValue(format!("Unknown preset: \"{}\"", name))
+				})?;
+			preset_args = with_preset(args, preset);
+			&preset_args
+		}
+		None => args,
+	};
+	match args.get.as_deref() {
+		Some(value) => print_state(args, server, config, value),
+		None => parse_args(args, server, config),
+	}
+}
+
+/// Fills the flags that weren't passed on the command line with the values of the preset
+fn with_preset(args: &ArgsClient, preset: &ClientPreset) -> ArgsClient {
+	let mut args = args.clone();
+
+	args.monitor = args.monitor.or(preset.monitor.clone());
+	args.device = args.device.or(preset.device.clone());
+	args.max_volume = args.max_volume.or(preset.max_volume.map(|v| v.to_string()));
+	args.min_brightness = args
+		.min_brightness
+		.or(preset.min_brightness.map(|v| v.to_string()));
+	args.player = args.player.or(preset.player.clone());
+	args.custom_icon = args.custom_icon.or(preset.custom_icon.clone());
+	args.custom_progress_text = args
+		.custom_progress_text
+		.or(preset.custom_progress_text.clone());
+	args.caps_lock |= preset.caps_lock.unwrap_or(false);
+	args.num_lock |= preset.num_lock.unwrap_or(false);
+	args.scroll_lock |= preset.scroll_lock.unwrap_or(false);
+	args.output_volume = args.output_volume.or(preset.output_volume.clone());
+	args.input_volume = args.input_volume.or(preset.input_volume.clone());
+	args.brightness = args.brightness.or(preset.brightness.clone());
+	args.playerctl = args.playerctl.or(preset.playerctl.clone());
+	args.custom_message = args.custom_message.or(preset.custom_message.clone());
+	args.custom_progress = args
+		.custom_progress
+		.or(preset.custom_progress.map(|v| v.to_string()));
+	args.custom_segmented_progress = args
+		.custom_segmented_progress
+		.or(preset.custom_segmented_progress.clone());
+	args
+}
+
+/// Reads one command per line from stdin and sends it through the same connection.
+/// A line is a list of flags without the leading dashes, where a flag that takes a value
+/// is followed by its value or written as `flag=value`. Ex: "custom-progress 0.42 text=Copying"
+fn listen(args: &ArgsClient, server: &Server, config: &ClientConfig) {
+	let mut command = ArgsClient::command();
+	command.build();
+	for (index, line) in std::io::stdin().lines().enumerate() {
+		let line = match line {
+			Ok(line) => line,
+			Err(error) => {
+				eprintln!("Could not read stdin: {}", error);
 				std::process::exit(1);
 			}
-		},
-		Err(err) => {
-			eprintln!("Dbus error: {}", err);
-			std::process::exit(1);
+		};
+		let Some(words) = shlex::split(&line) else {
+			eprintln!("Line {}: Unbalanced quotes", index + 1);
+			continue;
+		};
+		if words.is_empty() {
+			continue;
+		}
+
+		let argv =
+			std::iter::once(env!("CARGO_BIN_NAME").to_owned()).chain(listen_flags(&command, words));
+		let mut line_args = match ArgsClient::try_parse_from(argv) {
+			Ok(line_args) => line_args,
+			Err(error) => {
+				let error = error.to_string();
+				eprintln!(
+					"Line {}: {}",
+					index + 1,
+					error.lines().next().unwrap_or_default()
+				);
+				continue;
+			}
+		};
+		line_args.json |= args.json;
+		if let Err(error) = run(&line_args, server, config) {
+			eprintln!("Line {}: {}", index + 1, error);
+		}
+	}
+}
+
+/// Turns the words of a --listen line into flags. The word after a flag that takes a value
+/// is attached as `--flag=value`, so values that look like flags or contain a `=` are kept
+fn listen_flags(command: &Command, words: Vec<String>) -> Vec<String> {
+	let mut flags: Vec<String> = Vec::new();
+	let mut expects_value = false;
+	for word in words {
+		match flags.last_mut() {
+			Some(flag) if expects_value => {
+				flag.push('=');
+				flag.push_str(&word);
+				expects_value = false;
+			}
+			_ => {
+				expects_value = !word.contains('=') && flag_takes_value(command, &word);
+				flags.push(format!("--{}", word));
+			}
+		}
+	}
+	flags
+}
+
+/// Whether the long flag, or one of its aliases, takes a value
+fn flag_takes_value(command: &Command, name: &str) -> bool {
+	command.get_arguments().any(|arg| {
+		let matches_name = arg.get_long() == Some(name)
+			|| arg
+				.get_all_aliases()
+				.is_some_and(|aliases| aliases.contains(&name));
+		matches_name && arg.get_action().takes_values()
+	})
+}
+
+/// Prints every OSD displayed by the server, as JSON with --json
+fn watch(args: &ArgsClient, server: &Server) -> Result<(), ActionError> {
+	let Server::DBus(proxy) = server else {
+		return Err(ActionError::Failed(
+			"The OSD events are only sent through the D-Bus session bus".to_owned(),
+		));
+	};
+	// The lock keys come straight from the backend, even when the server doesn't show them
+	let json = args.json;
+	std::thread::spawn(move || {
+		if let Err(error) = watch_key_presses(json) {
+			eprintln!("Could not watch swayosd-libinput-backend: {}", error);
+		}
+	});
+	for signal in proxy.receive_osd_shown()? {
+		let signal_args = signal.args()?;
+		let event = OsdEvent {
+			kind: signal_args.kind.to_owned(),
+			value: signal_args.value,
+			max: signal_args.max,
+			muted: signal_args.muted,
+			label: signal_args.label.to_owned(),
+			icon: signal_args.icon.to_owned(),
+			device: signal_args.device.to_owned(),
+			monitor: signal_args.monitor.to_owned(),
+		};
+		print_event(json, &event)?;
+	}
+	Ok(())
+}
+
+/// Prints the lock key changes from the KeyPressed signal of swayosd-libinput-backend
+fn watch_key_presses(json: bool) -> Result<(), ActionError> {
+	let connection = Connection::system()?;
+	let proxy = BackendProxyBlocking::new(&connection)?;
+	for signal in proxy.receive_key_pressed()? {
+		// Older backends don't send the seat and device path
+		let Ok(signal_args) = signal.args() else {
+			continue;
+		};
+		if let Some(event) = key_event(
+			signal_args.key_code,
+			signal_args.state,
+			signal_args.device_path,
+		) {
+			print_event(json, &event)?;
 		}
+	}
+	Ok(())
+}
+
+/// The event of a lock key. Other keys and keyboards without the LED are skipped
+fn key_event(key_code: u16, state: i32, device_path: &str) -> Option<OsdEvent> {
+	let kind = match evdev_rs::enums::int_to_ev_key(key_code as u32)? {
+		EV_KEY::KEY_CAPSLOCK => "caps-lock",
+		EV_KEY::KEY_NUMLOCK => "num-lock",
+		EV_KEY::KEY_SCROLLLOCK => "scroll-lock",
+		_ => return None,
 	};
+	if state < 0 {
+		return None;
+	}
+	Some(OsdEvent {
+		kind: kind.to_owned(),
+		value: state as f64,
+		max: 1.0,
+		device: device_path.to_owned(),
+		..Default::default()
+	})
+}
 
-	parse_args(&args, &proxy);
+fn print_event(json: bool, event: &OsdEvent) -> Result<(), ActionError> {
+	if json {
+		println!("{}", to_json(event)?);
+	} else {
+		println!("{}", event);
+	}
+	Ok(())
 }
 
-fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
+fn print_state(
+	args: &ArgsClient,
+	server: &Server,
+	config: &ClientConfig,
+	value: &str,
+) -> Result<(), ActionError> {
+	let device =
+		|default: &Option<String>| args.device.clone().or(default.clone()).unwrap_or_default();
+	let (text, state) = match value {
+		"output-volume" | "input-volume" => {
+			let (kind, default) = if value == "output-volume" {
+				("sink", &config.sink_device)
+			} else {
+				("source", &config.source_device)
+			};
+			let state = server.get_volume(kind, &device(default))?;
+			(state.volume.to_string(), to_json(&state)?)
+		}
+		"brightness" => {
+			let state = server.get_brightness(&device(&config.brightness_device))?;
+			(state.percent.to_string(), to_json(&state)?)
+		}
+		"caps-lock" | "num-lock" | "scroll-lock" => {
+			let state = server.get_lock_state(value)?;
+			let text = if state.state { "on" } else { "off" };
+			(text.to_owned(), to_json(&state)?)
+		}
+		value => {
+			return Err(ActionError::InvalidValue(format!(
+				"Unknown value: \"{}\"",
+				value
+			)));
+		}
+	};
+	if args.json {
+		println!("{}", state);
+	} else {
+		println!("{}", text);
+	}
+	Ok(())
+}
+
+fn parse_args(
+	args: &ArgsClient,
+	server: &Server,
+	config: &ClientConfig,
+) -> Result<(), ActionError> {
 	let mut actions: Vec<(ArgTypes, Option<String>)> = Vec::new();
 
 	//
@@ -71,7 +647,13 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 	if let Some(value) = args.max_volume.to_owned() {
 		match value.parse::<u8>() {
 			Ok(_) => actions.push((ArgTypes::MaxVolume, Some(value))),
-			Err(_) => eprintln!("{} is not a number between 0 and {}!", value, u8::MAX),
+			Err(_) => {
+				return Err(ActionError::InvalidValue(format!(
+					"{} is not a number between 0 and {}!",
+					value,
+					u8::MAX
+				)));
+			}
 		}
 	}
 	// Custom icon
@@ -90,13 +672,30 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 	if let Some(value) = args.custom_progress_text.to_owned() {
 		actions.push((ArgTypes::CustomProgressText, Some(value)));
 	}
+	// Progress ID
+	if let Some(value) = args.id.to_owned() {
+		actions.push((ArgTypes::ProgressId, Some(value)));
+	}
+	// Persistent progress
+	if args.persistent {
+		actions.push((ArgTypes::Persistent, None));
+	}
+	// Kinds shown while inhibited
+	if let Some(value) = args.inhibit_shown_kinds.to_owned() {
+		actions.push((ArgTypes::InhibitShownKinds, Some(value)));
+	}
 	// Min Brightness
 	if let Some(value) = args.min_brightness.to_owned() {
 		match value.parse::<u8>() {
 			Ok(value @ 0u8..=100u8) => {
 				actions.push((ArgTypes::MinBrightness, Some(value.to_string())))
 			}
-			_ => eprintln!("{} is not a number between 0 and {}!", value, 100),
+			_ => {
+				return Err(ActionError::InvalidValue(format!(
+					"{} is not a number between 0 and {}!",
+					value, 100
+				)));
+			}
 		}
 	}
 
@@ -129,16 +728,12 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 		actions.push((ArgTypes::ScrollLock, Some(value)));
 	}
 	// Output volume
-	if let Some(value) = args.output_volume.as_deref()
-		&& let Ok(parsed) = volume_parser(false, value)
-	{
-		actions.push(parsed);
+	if let Some(value) = args.output_volume.as_deref() {
+		actions.push(volume_parser(false, value)?);
 	}
 	// Input volume
-	if let Some(value) = args.input_volume.as_deref()
-		&& let Ok(parsed) = volume_parser(true, value)
-	{
-		actions.push(parsed);
+	if let Some(value) = args.input_volume.as_deref() {
+		actions.push(volume_parser(true, value)?);
 	}
 	// Brightness
 	if let Some(value) = args.brightness.as_deref() {
@@ -146,21 +741,21 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 		let value = match (value, value.parse::<i8>()) {
 			// Parse custom step values
 			(_, Ok(num)) => match value.get(..1) {
-				Some("+") => Some((ArgTypes::BrightnessRaise, Some(num.to_string()))),
-				Some("-") => Some((ArgTypes::BrightnessLower, Some(num.abs().to_string()))),
-				_ => Some((ArgTypes::BrightnessSet, Some(num.to_string()))),
+				Some("+") => (ArgTypes::BrightnessRaise, Some(num.to_string())),
+				Some("-") => (ArgTypes::BrightnessLower, Some(num.abs().to_string())),
+				_ => (ArgTypes::BrightnessSet, Some(num.to_string())),
 			},
 
-			("raise", _) => Some((ArgTypes::BrightnessRaise, None)),
-			("lower", _) => Some((ArgTypes::BrightnessLower, None)),
+			("raise", _) => (ArgTypes::BrightnessRaise, None),
+			("lower", _) => (ArgTypes::BrightnessLower, None),
 			(e, _) => {
-				eprintln!("Unknown brightness mode: \"{}\"!...", e);
-				None
+				return Err(ActionError::InvalidValue(format!(
+					"Unknown brightness mode: \"{}\"!...",
+					e
+				)));
 			}
 		};
-		if let Some(value) = value {
-			actions.push(value);
-		}
+		actions.push(value);
 	}
 	// Playerctl
 	if let Some(value) = args.playerctl.as_deref() {
@@ -168,7 +763,12 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 			"play-pause" | "play" | "pause" | "next" | "prev" | "previous" | "shuffle" | "stop" => {
 				actions.push((ArgTypes::Playerctl, Some(value.to_string())));
 			}
-			x => eprintln!("Unknown Playerctl command: \"{}\"!...", x),
+			x => {
+				return Err(ActionError::InvalidValue(format!(
+					"Unknown Playerctl command: \"{}\"!...",
+					x
+				)));
+			}
 		}
 	}
 	// Custom message
@@ -177,10 +777,8 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 	}
 	// Custom progress
 	if let Some(value) = args.custom_progress.as_deref() {
-		match value.parse::<f64>() {
-			Ok(_) => actions.push((ArgTypes::CustomProgress, Some(value.to_string()))),
-			Err(_) => eprintln!("{} is not a number between 0.0 and 1.0!", value),
-		}
+		global_utils::progress_parser(value).map_err(ActionError::InvalidValue)?;
+		actions.push((ArgTypes::CustomProgress, Some(value.to_string())));
 	}
 	// Custom segmented progress
 	if let Some(value) = args.custom_segmented_progress.as_deref() {
@@ -189,17 +787,199 @@ fn parse_args(args: &ArgsClient, proxy: &ServerProxyBlocking<'_>) {
 				ArgTypes::CustomSegmentedProgress,
 				Some(format!("{}:{}", value, n_segments)),
 			)),
-			Err(msg) => eprintln!("{}", msg),
+			Err(msg) => return Err(ActionError::InvalidValue(msg)),
+		}
+	}
+	// Close progress
+	if let Some(value) = args.close.to_owned() {
+		actions.push((ArgTypes::CloseProgress, Some(value)));
+	}
+	// Inhibit
+	if let Some(value) = args.inhibit.as_deref() {
+		match value {
+			"on" | "off" | "toggle" => actions.push((ArgTypes::Inhibit, Some(value.to_string()))),
+			x => {
+				return Err(ActionError::InvalidValue(format!(
+					"Unknown inhibit mode: \"{}\"!...",
+					x
+				)));
+			}
 		}
 	}
 
 	// execute the sorted actions
+	let mut context = ActionContext::default();
 	for (arg_type, data) in actions {
-		let _ = proxy.handle_action(arg_type.to_string(), data.unwrap_or(String::new()));
+		if context.set_modifier(&arg_type, &data) {
+			continue;
+		}
+		let (context, data) = with_defaults(config, &context, &arg_type, data);
+		if let Some(state) = server.send_action(&context, arg_type, data)?
+			&& args.json
+		{
+			println!("{}", state);
+		}
 	}
+	Ok(())
+}
+
+/// Fills the modifiers and the step that weren't passed as flags with the client config
+fn with_defaults(
+	config: &ClientConfig,
+	context: &ActionContext,
+	arg_type: &ArgTypes,
+	data: Option<String>,
+) -> (ActionContext, Option<String>) {
+	let (device, step) = match arg_type {
+		ArgTypes::SinkVolumeRaise | ArgTypes::SinkVolumeLower | ArgTypes::SinkVolumeMuteToggle => {
+			(&config.sink_device, config.volume_step)
+		}
+		ArgTypes::SourceVolumeRaise
+		| ArgTypes::SourceVolumeLower
+		| ArgTypes::SourceVolumeMuteToggle => (&config.source_device, config.volume_step),
+		ArgTypes::BrightnessRaise | ArgTypes::BrightnessLower | ArgTypes::BrightnessSet => {
+			(&config.brightness_device, config.brightness_step)
+		}
+		_ => (&None, None),
+	};
+	let mut context = context.clone();
+	context.device_name = context.device_name.or(device.clone());
+	context.monitor_name = context.monitor_name.or(config.monitor.clone());
+	context.max_volume = context.max_volume.or(config.max_volume);
+	context.min_brightness = context.min_brightness.or(config.min_brightness);
+
+	// Only raise and lower take a step, the other actions keep their value
+	let data = match arg_type {
+		ArgTypes::SinkVolumeRaise
+		| ArgTypes::SinkVolumeLower
+		| ArgTypes::SourceVolumeRaise
+		| ArgTypes::SourceVolumeLower
+		| ArgTypes::BrightnessRaise
+		| ArgTypes::BrightnessLower => data.or(step.map(|step| step.to_string())),
+		_ => data,
+	};
+	(context, data)
+}
+
+fn send_dbus_action(
+	proxy: &ServerProxyBlocking<'_>,
+	context: &ActionContext,
+	arg_type: ArgTypes,
+	data: Option<String>,
+) -> Result<Option<serde_json::Value>, ActionError> {
+	let device = context.device_name.as_deref().unwrap_or_default();
+	let monitor = context.monitor_name.as_deref().unwrap_or_default();
+	let icon = context.icon_name.as_deref().unwrap_or_default();
+	let text = context.progress_text.as_deref().unwrap_or_default();
+	let player = context.player.as_deref().unwrap_or_default();
+	let max = context.max_volume.map_or(-1, i32::from);
+	let min = context.min_brightness.map_or(-1, |min| min as i32);
+
+	let data = data.unwrap_or_default();
+	let step = || global_utils::step_parser(&data).map_err(ActionError::InvalidValue);
+	let state = match arg_type {
+		ArgTypes::SinkVolumeRaise => {
+			to_json(&proxy.set_sink_volume(device, "raise", step()?, max, monitor)?)?
+		}
+		ArgTypes::SinkVolumeLower => {
+			to_json(&proxy.set_sink_volume(device, "lower", step()?, max, monitor)?)?
+		}
+		ArgTypes::SinkVolumeMuteToggle => {
+			to_json(&proxy.set_sink_volume(device, "mute-toggle", -1, max, monitor)?)?
+		}
+		ArgTypes::SourceVolumeRaise => {
+			to_json(&proxy.set_source_volume(device, "raise", step()?, max, monitor)?)?
+		}
+		ArgTypes::SourceVolumeLower => {
+			to_json(&proxy.set_source_volume(device, "lower", step()?, max, monitor)?)?
+		}
+		ArgTypes::SourceVolumeMuteToggle => {
+			to_json(&proxy.set_source_volume(device, "mute-toggle", -1, max, monitor)?)?
+		}
+		ArgTypes::BrightnessRaise => {
+			to_json(&proxy.set_brightness(device, "raise", step()?, min, monitor)?)?
+		}
+		ArgTypes::BrightnessLower => {
+			to_json(&proxy.set_brightness(device, "lower", step()?, min, monitor)?)?
+		}
+		ArgTypes::BrightnessSet => {
+			to_json(&proxy.set_brightness(device, "set", step()?, min, monitor)?)?
+		}
+		ArgTypes::CapsLock | ArgTypes::NumLock | ArgTypes::ScrollLock => {
+			let key = match arg_type {
+				ArgTypes::CapsLock => "caps-lock",
+				ArgTypes::NumLock => "num-lock",
+				_ => "scroll-lock",
+			};
+			let state = proxy.show_key_lock(key, -1, &data, monitor)?;
+			to_json(&KeyLockState {
+				key: key.to_owned(),
+				state,
+			})?
+		}
+		ArgTypes::Playerctl => to_json(&proxy.control_player(&data, player, monitor)?)?,
+		ArgTypes::CustomMessage => {
+			proxy.show_custom_message(&data, icon, monitor)?;
+			return Ok(None);
+		}
+		ArgTypes::CustomProgress => {
+			let fraction =
+				global_utils::progress_parser(&data).map_err(ActionError::InvalidValue)?;
+			let fraction = match context.progress_id.as_deref() {
+				Some(id) => proxy.show_tracked_progress(
+					id,
+					fraction,
+					text,
+					icon,
+					monitor,
+					context.persistent,
+				)?,
+				None => proxy.show_custom_progress(fraction, text, icon, monitor)?,
+			};
+			to_json(&ProgressState { fraction })?
+		}
+		ArgTypes::CustomSegmentedProgress => {
+			let (value, n_segments) = global_utils::segmented_progress_parser(&data)
+				.map_err(ActionError::InvalidValue)?;
+			let (value, n_segments) = match context.progress_id.as_deref() {
+				Some(id) => proxy.show_tracked_segmented_progress(
+					id,
+					value,
+					n_segments,
+					text,
+					icon,
+					monitor,
+					context.persistent,
+				)?,
+				None => {
+					proxy.show_custom_segmented_progress(value, n_segments, text, icon, monitor)?
+				}
+			};
+			to_json(&SegmentedProgressState { value, n_segments })?
+		}
+		ArgTypes::CloseProgress => {
+			proxy.close_progress(&data)?;
+			return Ok(None);
+		}
+		ArgTypes::Inhibit => {
+			if let Some(kinds) = &context.inhibit_shown_kinds {
+				proxy.set_inhibit_shown_kinds(kinds)?;
+			}
+			to_json(&proxy.inhibit(&data)?)?
+		}
+		arg_type => {
+			proxy.handle_action(arg_type.to_string(), data)?;
+			return Ok(None);
+		}
+	};
+	Ok(Some(state))
 }
 
-fn volume_parser(is_sink: bool, value: &str) -> Result<(ArgTypes, Option<String>), i32> {
+fn to_json<T: Serialize>(state: &T) -> Result<serde_json::Value, ActionError> {
+	serde_json::to_value(state).map_err(|error| ActionError::Failed(error.to_string()))
+}
+
+fn volume_parser(is_source: bool, value: &str) -> Result<(ArgTypes, Option<String>), ActionError> {
 	let mut v = match (value, value.parse::<i8>()) {
 		// Parse custom step values
 		(_, Ok(num)) => (
@@ -214,11 +994,13 @@ fn volume_parser(is_sink: bool, value: &str) -> Result<(ArgTypes, Option<String>
 		("lower", _) => (ArgTypes::SinkVolumeLower, None),
 		("mute-toggle", _) => (ArgTypes::SinkVolumeMuteToggle, None),
 		(e, _) => {
-			eprintln!("Unknown output volume mode: \"{}\"!...", e);
-			return Err(1);
+			return Err(ActionError::InvalidValue(format!(
+				"Unknown volume mode: \"{}\"!...",
+				e
+			)));
 		}
 	};
-	if is_sink {
+	if is_source {
 		if v.0 == ArgTypes::SinkVolumeRaise {
 			v.0 = ArgTypes::SourceVolumeRaise;
 		} else if v.0 == ArgTypes::SinkVolumeLower {
@@ -229,3 +1011,94 @@ fn volume_parser(is_sink: bool, value: &str) -> Result<(ArgTypes, Option<String>
 	}
 	Ok(v)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn flags(line: &str) -> Vec<String> {
+		let mut command = ArgsClient::command();
+		command.build();
+		listen_flags(&command, shlex::split(line).unwrap())
+	}
+
+	fn parse(line: &str) -> Result<ArgsClient, clap::Error> {
+		let argv = std::iter::once(env!("CARGO_BIN_NAME").to_owned()).chain(flags(line));
+		ArgsClient::try_parse_from(argv)
+	}
+
+	#[test]
+	fn listen_flags_attach_values() {
+		assert_eq!(
+			flags("custom-progress 0.42 text=Copying"),
+			["--custom-progress=0.42", "--text=Copying"]
+		);
+		let args = parse("custom-progress 0.42 text=Copying").unwrap();
+		assert_eq!(args.custom_progress.as_deref(), Some("0.42"));
+		assert_eq!(args.custom_progress_text.as_deref(), Some("Copying"));
+	}
+
+	#[test]
+	fn listen_flags_keep_boolean_flags_separate() {
+		assert_eq!(
+			flags("caps-lock output-volume +5"),
+			["--caps-lock", "--output-volume=+5"]
+		);
+		let args = parse("caps-lock output-volume +5").unwrap();
+		assert!(args.caps_lock);
+		assert_eq!(args.output_volume.as_deref(), Some("+5"));
+
+		assert_eq!(
+			flags("persistent custom-progress 0.3"),
+			["--persistent", "--custom-progress=0.3"]
+		);
+		let args = parse("id job persistent custom-progress 0.3").unwrap();
+		assert_eq!(args.id.as_deref(), Some("job"));
+		assert!(args.persistent);
+		assert_eq!(args.custom_progress.as_deref(), Some("0.3"));
+	}
+
+	#[test]
+	fn listen_flags_keep_values_with_equal_signs_and_dashes() {
+		let args = parse("custom-message a=b").unwrap();
+		assert_eq!(args.custom_message.as_deref(), Some("a=b"));
+
+		let args = parse("output-volume -5").unwrap();
+		assert_eq!(args.output_volume.as_deref(), Some("-5"));
+
+		let args = parse("custom-message 'two words' icon=dialog-information").unwrap();
+		assert_eq!(args.custom_message.as_deref(), Some("two words"));
+		assert_eq!(args.custom_icon.as_deref(), Some("dialog-information"));
+	}
+
+	#[test]
+	fn listen_flags_report_missing_values_and_unknown_flags() {
+		assert!(parse("output-volume").is_err());
+		assert!(parse("volume-up").is_err());
+	}
+
+	#[test]
+	fn key_event_reports_lock_keys() {
+		let event = key_event(EV_KEY::KEY_CAPSLOCK as u16, 1, "/dev/input/event3").unwrap();
+		assert_eq!(event.kind, "caps-lock");
+		assert_eq!(event.value, 1.0);
+		assert_eq!(event.max, 1.0);
+		assert_eq!(event.device, "/dev/input/event3");
+		assert_eq!(event.to_string(), "caps-lock 1/1 /dev/input/event3");
+
+		let event = key_event(EV_KEY::KEY_NUMLOCK as u16, 0, "").unwrap();
+		assert_eq!(event.to_string(), "num-lock 0/1");
+		assert_eq!(
+			key_event(EV_KEY::KEY_SCROLLLOCK as u16, 1, "")
+				.unwrap()
+				.kind,
+			"scroll-lock"
+		);
+	}
+
+	#[test]
+	fn key_event_skips_other_keys_and_unknown_states() {
+		assert!(key_event(EV_KEY::KEY_A as u16, 1, "").is_none());
+		assert!(key_event(EV_KEY::KEY_CAPSLOCK as u16, -1, "").is_none());
+	}
+}
@@ -8,5 +8,7 @@ pub mod user;
 pub const DBUS_PATH: &str = "/org/erikreider/swayosd";
 pub const DBUS_BACKEND_NAME: &str = "org.erikreider.swayosd";
 pub const DBUS_SERVER_NAME: &str = "org.erikreider.swayosd-server";
+/// Bumped whenever the methods of the server interface change
+pub const DBUS_INTERFACE_VERSION: u32 = 3;
 
 pub const APPLICATION_NAME: &str = "org.erikreider.swayosd";
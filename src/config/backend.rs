@@ -7,6 +7,8 @@ use std::path::PathBuf;
 #[serde(deny_unknown_fields)]
 pub struct InputBackendConfig {
 	pub ignore_caps_lock_key: Option<bool>,
+	/// The seats to read the keyboards of. Defaults to all the seats known to logind
+	pub seats: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Default, Debug)]
@@ -1,13 +1,49 @@
 use gtk::glib::system_config_dirs;
 use gtk::glib::user_config_dir;
 use serde_derive::Deserialize;
+use std::collections::HashMap;
 use std::error::Error;
 use std::path::Path;
 use std::path::PathBuf;
 
 #[derive(Deserialize, Default, Debug)]
 #[serde(deny_unknown_fields)]
-pub struct ClientConfig {}
+pub struct ClientConfig {
+	pub monitor: Option<String>,
+	pub sink_device: Option<String>,
+	pub source_device: Option<String>,
+	pub brightness_device: Option<String>,
+	pub volume_step: Option<u8>,
+	pub brightness_step: Option<u8>,
+	pub max_volume: Option<u8>,
+	pub min_brightness: Option<u32>,
+	pub presets: Option<HashMap<String, ClientPreset>>,
+	pub direct_fallback: Option<bool>,
+	pub direct_notifications: Option<bool>,
+}
+
+/// Flags applied by `swayosd-client --preset NAME`. Uses the same values as the flags
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ClientPreset {
+	pub monitor: Option<String>,
+	pub device: Option<String>,
+	pub max_volume: Option<u8>,
+	pub min_brightness: Option<u32>,
+	pub player: Option<String>,
+	pub custom_icon: Option<String>,
+	pub custom_progress_text: Option<String>,
+	pub caps_lock: Option<bool>,
+	pub num_lock: Option<bool>,
+	pub scroll_lock: Option<bool>,
+	pub output_volume: Option<String>,
+	pub input_volume: Option<String>,
+	pub brightness: Option<String>,
+	pub playerctl: Option<String>,
+	pub custom_message: Option<String>,
+	pub custom_progress: Option<f64>,
+	pub custom_segmented_progress: Option<String>,
+}
 
 #[derive(Deserialize, Default, Debug, Clone)]
 #[serde(deny_unknown_fields)]
@@ -19,6 +55,32 @@ pub struct ServerConfig {
 	pub playerctl_format: Option<String>,
 	pub min_brightness: Option<u32>,
 	pub keyboard_backlight: Option<bool>,
+	pub wob_fifo: Option<PathBuf>,
+	pub wob_styles: Option<HashMap<String, WobStyle>>,
+	pub notifications: Option<bool>,
+	pub gnome_shell_osd: Option<bool>,
+	pub kde_osd_service: Option<bool>,
+	pub watch_config: Option<bool>,
+	pub inhibit_shown_kinds: Option<Vec<String>>,
+	pub audio_backend: Option<AudioBackend>,
+}
+
+/// The sound server used for the volume actions
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackend {
+	/// PulseAudio (or pipewire-pulse), falling back to PipeWire
+	#[default]
+	Auto,
+	Pulse,
+	PipeWire,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WobStyle {
+	pub icon: Option<String>,
+	pub text: Option<String>,
 }
 
 #[derive(Deserialize, Default, Debug)]
@@ -30,7 +92,7 @@ pub struct UserConfig {
 	pub client: ClientConfig,
 }
 
-fn find_user_config() -> Option<PathBuf> {
+pub fn find_user_config() -> Option<PathBuf> {
 	let path = user_config_dir().join("swayosd").join("config.toml");
 	if path.exists() {
 		return Some(path);
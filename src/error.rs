@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+
+use zbus::DBusError;
+
+/// The errors returned by the server interface.
+/// The D-Bus error name is the variant name prefixed with `org.erikreider.swayosd.Error.`
+#[derive(DBusError, Clone, Debug)]
+#[zbus(prefix = "org.erikreider.swayosd.Error")]
+pub enum ActionError {
+	#[zbus(error)]
+	ZBus(zbus::Error),
+	/// The requested sink, source or brightness device doesn't exist
+	NoSuchDevice(String),
+	/// No MPRIS player is available
+	NoPlayer(String),
+	/// None of the brightness backends could be used
+	BrightnessBackendUnavailable(String),
+	/// The action was sent with an invalid mode or value
+	InvalidValue(String),
+	/// Any other failure
+	Failed(String),
+}
+
+const ERROR_PREFIX: &str = "org.erikreider.swayosd.Error.";
+
+impl ActionError {
+	/// Rebuilds the error from its D-Bus error name and message
+	pub fn from_name(name: &str, message: String) -> Self {
+		match name.strip_prefix(ERROR_PREFIX) {
+			Some("NoSuchDevice") => ActionError::NoSuchDevice(message),
+			Some("NoPlayer") => ActionError::NoPlayer(message),
+			Some("BrightnessBackendUnavailable") => {
+				ActionError::BrightnessBackendUnavailable(message)
+			}
+			Some("InvalidValue") => ActionError::InvalidValue(message),
+			_ => ActionError::Failed(message),
+		}
+	}
+
+	/// The exit code used by swayosd-client when an action fails
+	pub fn exit_code(&self) -> i32 {
+		match self {
+			ActionError::ZBus(_) | ActionError::Failed(_) => 1,
+			ActionError::InvalidValue(_) => 2,
+			ActionError::NoSuchDevice(_) => 3,
+			ActionError::NoPlayer(_) => 4,
+			ActionError::BrightnessBackendUnavailable(_) => 5,
+		}
+	}
+}
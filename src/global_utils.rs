@@ -17,6 +17,92 @@ pub fn segmented_progress_parser(ref_value: &str) -> Result<(u32, u32), String>
 	}
 }
 
+/// Parses the step of a raise/lower action or the value of a set action.
+/// An empty value keeps the default and is returned as -1, like in the D-Bus methods
+pub fn step_parser(ref_value: &str) -> Result<i32, String> {
+	if ref_value.is_empty() {
+		return Ok(-1);
+	}
+	match ref_value.parse::<u32>() {
+		Ok(value) if value <= i32::MAX as u32 => Ok(value as i32),
+		_ => Err(format!(
+			"Value {} not valid for the step. Must be a positive integer",
+			ref_value
+		)),
+	}
+}
+
+/// Parses the fraction of a custom progress. Values outside of 0.0 to 1.0 are clamped later
+pub fn progress_parser(ref_value: &str) -> Result<f64, String> {
+	match ref_value.parse::<f64>() {
+		Ok(fraction) if fraction.is_finite() => Ok(fraction),
+		_ => Err(format!(
+			"{} is not a number between 0.0 and 1.0!",
+			ref_value
+		)),
+	}
+}
+
 pub fn div_round_u32(a: u32, b: u32) -> u32 {
 	(a + b / 2) / b
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn segmented_progress_parser_reads_value_and_segments() {
+		assert_eq!(segmented_progress_parser("3:10"), Ok((3, 10)));
+		assert_eq!(segmented_progress_parser("0:0"), Ok((0, 0)));
+		// The value isn't clamped here, the server clamps it to the number of segments
+		assert_eq!(segmented_progress_parser("12:10"), Ok((12, 10)));
+	}
+
+	#[test]
+	fn segmented_progress_parser_rejects_invalid_values() {
+		assert!(segmented_progress_parser("3").is_err());
+		assert!(segmented_progress_parser("").is_err());
+		assert!(segmented_progress_parser("-1:10").is_err());
+		assert!(segmented_progress_parser("3:ten").is_err());
+		assert!(segmented_progress_parser("3:10:2").is_err());
+		assert!(segmented_progress_parser("0.5:10").is_err());
+	}
+
+	#[test]
+	fn step_parser_keeps_the_default_for_empty_steps() {
+		assert_eq!(step_parser(""), Ok(-1));
+		assert_eq!(step_parser("0"), Ok(0));
+		assert_eq!(step_parser("5"), Ok(5));
+	}
+
+	#[test]
+	fn step_parser_rejects_invalid_steps() {
+		assert!(step_parser("five").is_err());
+		assert!(step_parser("-5").is_err());
+		assert!(step_parser("2.5").is_err());
+		assert!(step_parser("4294967295").is_err());
+	}
+
+	#[test]
+	fn progress_parser_reads_fractions() {
+		assert_eq!(progress_parser("0.42"), Ok(0.42));
+		assert_eq!(progress_parser("1"), Ok(1.0));
+		assert_eq!(progress_parser("1.5"), Ok(1.5));
+	}
+
+	#[test]
+	fn progress_parser_rejects_invalid_fractions() {
+		assert!(progress_parser("").is_err());
+		assert!(progress_parser("half").is_err());
+		assert!(progress_parser("NaN").is_err());
+		assert!(progress_parser("inf").is_err());
+	}
+
+	#[test]
+	fn div_round_u32_rounds_to_nearest() {
+		assert_eq!(div_round_u32(10, 4), 3);
+		assert_eq!(div_round_u32(9, 4), 2);
+		assert_eq!(div_round_u32(0, 3), 0);
+	}
+}
@@ -7,11 +7,15 @@ pub struct DbusServer;
 
 #[interface(name = "org.erikreider.swayosd")]
 impl DbusServer {
+	/// The seat and device path were added after the first two arguments,
+	/// so older servers can still read the signal
 	#[zbus(signal)]
 	pub async fn key_pressed(
 		signal_ctxt: &SignalEmitter<'_>,
 		key_code: u16,
 		state: i32,
+		seat: &str,
+		device_path: &str,
 	) -> zbus::Result<()>;
 }
 
@@ -20,9 +20,12 @@ use zbus::object_server::InterfaceRef;
 #[path = "../config.rs"]
 mod config;
 mod dbus_server;
+#[path = "../server/login1.rs"]
+mod login1;
 
 struct EventInfo {
 	device_path: String,
+	seat: String,
 	ev_key: EV_KEY,
 }
 
@@ -53,18 +56,41 @@ fn main() -> Result<(), zbus::Error> {
 	let object_server = connection.object_server();
 	let iface_ref = task::block_on(object_server.interface::<_, DbusServer>(DBUS_PATH))?;
 
-	// Init libinput
-	let mut input = Libinput::new_with_udev(Interface);
-	input
-		.udev_assign_seat("seat0")
-		.expect("Could not assign seat0");
-	let fd = input.as_raw_fd();
-	assert!(fd != -1);
-	let borrowed_fd = unsafe { BorrowedFd::borrow_raw(fd) };
-	let pollfd = PollFd::new(borrowed_fd, PollFlags::POLLIN);
-	while poll(&mut [pollfd.clone()], None::<u8>).is_ok() {
-		if let Err(error) = event(&input_config, &mut input, &iface_ref) {
-			eprintln!("Event error: {:?}", error);
+	// Init libinput, a context per seat
+	let seats = match input_config.seats.clone() {
+		Some(seats) => seats,
+		None => task::block_on(list_seats()).unwrap_or_else(|error| {
+			eprintln!("Could not list the logind seats, using seat0: {}", error);
+			vec!["seat0".to_owned()]
+		}),
+	};
+	let mut inputs = Vec::new();
+	for seat in seats {
+		let mut input = Libinput::new_with_udev(Interface);
+		if input.udev_assign_seat(&seat).is_err() {
+			eprintln!("Could not assign {}", seat);
+			continue;
+		}
+		inputs.push(input);
+	}
+	if inputs.is_empty() {
+		eprintln!("Error: No seat could be assigned");
+		std::process::exit(1)
+	}
+	let mut pollfds: Vec<PollFd> = inputs
+		.iter()
+		.map(|input| {
+			let fd = input.as_raw_fd();
+			assert!(fd != -1);
+			let borrowed_fd = unsafe { BorrowedFd::borrow_raw(fd) };
+			PollFd::new(borrowed_fd, PollFlags::POLLIN)
+		})
+		.collect();
+	while poll(&mut pollfds, None::<u8>).is_ok() {
+		for input in inputs.iter_mut() {
+			if let Err(error) = event(&input_config, input, &iface_ref) {
+				eprintln!("Event error: {:?}", error);
+			}
 		}
 	}
 
@@ -85,6 +111,7 @@ fn event(
 		if event.key_state() == KeyState::Pressed {
 			continue;
 		}
+		let seat = event.device().seat().physical_name().to_owned();
 		let device = match unsafe { event.device().udev_device() } {
 			Some(device) => device,
 			None => continue,
@@ -138,6 +165,7 @@ fn event(
 		{
 			let event_info = EventInfo {
 				device_path: path.to_owned(),
+				seat,
 				ev_key,
 			};
 			task::spawn(call(event_info, iface_ref.clone()));
@@ -150,7 +178,7 @@ async fn call(event_info: EventInfo, iface_ref: InterfaceRef<DbusServer>) {
 	// Wait for the LED value to change
 	sleep(Duration::from_millis(50)).await;
 
-	let Ok(device) = evdev_rs::Device::new_from_path(event_info.device_path) else {
+	let Ok(device) = evdev_rs::Device::new_from_path(&event_info.device_path) else {
 		return;
 	};
 
@@ -166,6 +194,8 @@ async fn call(event_info: EventInfo, iface_ref: InterfaceRef<DbusServer>) {
 		iface_ref.signal_emitter(),
 		event_info.ev_key as u16,
 		lock_state.unwrap_or(-1),
+		&event_info.seat,
+		&event_info.device_path,
 	)
 	.await;
 
@@ -173,3 +203,10 @@ async fn call(event_info: EventInfo, iface_ref: InterfaceRef<DbusServer>) {
 		eprintln!("Signal Error: {}", error)
 	}
 }
+
+/// The IDs of all the seats known to logind
+async fn list_seats() -> zbus::Result<Vec<String>> {
+	let proxy = login1::Login1::init().await?;
+	let seats = proxy.list_seats().await?;
+	Ok(seats.into_iter().map(|(id, _)| id).collect())
+}
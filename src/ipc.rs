@@ -0,0 +1,64 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use gtk::glib::user_runtime_dir;
+use serde_derive::{Deserialize, Serialize};
+use zbus::DBusError;
+
+use crate::error::ActionError;
+
+const SOCKET_NAME: &str = "swayosd.sock";
+
+/// The unix socket used when there's no D-Bus session bus
+pub fn socket_path() -> PathBuf {
+	user_runtime_dir().join(SOCKET_NAME)
+}
+
+/// A request sent over the unix socket as a single line of JSON.
+/// Ex: {"actions":[["DEVICE-NAME","alsa_output.pci"],["SINK-VOLUME-RAISE","5"]]}
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SocketRequest {
+	/// Activates the actions in order. The names are the same as for `HandleAction`
+	Actions(Vec<(String, Option<String>)>),
+	/// Same as `swayosd-client --get`
+	Get {
+		value: String,
+		device: Option<String>,
+	},
+}
+
+/// The reply to a `SocketRequest` as a single line of JSON.
+/// Ex: {"ok":{"key":"caps-lock","state":true}}
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SocketReply {
+	/// The resulting state, null if the actions didn't produce one
+	Ok(Option<serde_json::Value>),
+	Error {
+		name: String,
+		message: String,
+	},
+}
+
+impl From<Result<Option<serde_json::Value>, ActionError>> for SocketReply {
+	fn from(result: Result<Option<serde_json::Value>, ActionError>) -> Self {
+		match result {
+			Ok(state) => SocketReply::Ok(state),
+			Err(error) => SocketReply::Error {
+				name: error.name().to_string(),
+				message: error.description().unwrap_or_default().to_owned(),
+			},
+		}
+	}
+}
+
+impl From<SocketReply> for Result<Option<serde_json::Value>, ActionError> {
+	fn from(reply: SocketReply) -> Self {
+		match reply {
+			SocketReply::Ok(state) => Ok(state),
+			SocketReply::Error { name, message } => Err(ActionError::from_name(&name, message)),
+		}
+	}
+}
@@ -1,7 +1,6 @@
 use mpris::{Metadata, PlaybackStatus, Player, PlayerFinder};
 
 use super::config::user::ServerConfig;
-use crate::utils::get_player;
 use std::{error::Error, sync::Arc, thread::sleep, time::Duration};
 use PlaybackStatus::*;
 use PlayerctlAction::*;
@@ -33,16 +32,18 @@ pub struct Playerctl {
 	action: PlayerctlAction,
 	pub icon: Option<String>,
 	pub label: Option<String>,
+	pub player_name: Option<String>,
+	pub status: Option<String>,
 	fmt_str: Option<String>,
 }
 
 impl Playerctl {
 	pub fn new(
 		action: PlayerctlAction,
+		player: PlayerctlDeviceRaw,
 		config: Arc<ServerConfig>,
 	) -> Result<Playerctl, Box<dyn Error>> {
 		let playerfinder = PlayerFinder::new()?;
-		let player = get_player();
 		let player = match player {
 			PlayerctlDeviceRaw::None => PlayerctlDevice::Some(playerfinder.find_active()?),
 			PlayerctlDeviceRaw::Some(name) => {
@@ -63,16 +64,20 @@ impl Playerctl {
 			action,
 			icon: None,
 			label: None,
+			player_name: None,
+			status: None,
 			fmt_str,
 		})
 	}
 	pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
 		let mut metadata = None;
 		let mut icon = Err("some errro");
+		let mut info = None;
 		match &self.player {
 			PlayerctlDevice::Some(player) => {
 				icon = Ok(self.run_single(player)?);
 				metadata = self.get_metadata(player);
+				info = Some(Self::get_player_info(player));
 			}
 			PlayerctlDevice::All(players) => {
 				for player in players {
@@ -85,6 +90,9 @@ impl Playerctl {
 					if metadata.is_none() {
 						metadata = self.get_metadata(player);
 					}
+					if info.is_none() {
+						info = Some(Self::get_player_info(player));
+					}
 				}
 			}
 		};
@@ -92,6 +100,10 @@ impl Playerctl {
 		self.icon = Some(icon.unwrap_or("").to_string());
 		let label = metadata.map(|metadata| self.fmt_string(metadata));
 		self.label = label;
+		if let Some((player_name, status)) = info {
+			self.player_name = Some(player_name);
+			self.status = status;
+		}
 		Ok(())
 	}
 	fn run_single(&self, player: &Player) -> Result<&str, Box<dyn Error>> {
@@ -138,6 +150,16 @@ impl Playerctl {
 		};
 		Ok(out)
 	}
+	/// Gets the name and the playback status of the player after the action
+	fn get_player_info(player: &Player) -> (String, Option<String>) {
+		let bus = player.bus_name();
+		let name = bus.strip_prefix("org.mpris.MediaPlayer2.").unwrap_or(bus);
+		let status = player
+			.get_playback_status()
+			.ok()
+			.map(|status| format!("{:?}", status));
+		(name.to_owned(), status)
+	}
 	fn get_metadata(&self, player: &Player) -> Option<Metadata> {
 		match self.action {
 			Next | Prev => {
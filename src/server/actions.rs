@@ -0,0 +1,344 @@
+use async_channel::Sender;
+use zbus::fdo;
+
+use crate::argtypes::{ActionContext, ArgTypes};
+use crate::error::ActionError;
+use crate::state::{
+	BrightnessState, InhibitState, KeyLockState, PlayerState, ProgressState,
+	SegmentedProgressState, VolumeState,
+};
+use crate::utils::{BRIGHTNESS_CHANGE_DELTA, VOLUME_CHANGE_DELTA};
+
+/// The resulting state of an activated action
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionReply {
+	None,
+	Volume(VolumeState),
+	Brightness(BrightnessState),
+	KeyLock(KeyLockState),
+	Player(PlayerState),
+	Progress(f64),
+	SegmentedProgress(u32, u32),
+	Inhibit(InhibitState),
+}
+
+impl ActionReply {
+	/// The state in the same format as `swayosd-client --json`
+	pub fn to_json(&self) -> serde_json::Result<Option<serde_json::Value>> {
+		let value = match self {
+			ActionReply::None => return Ok(None),
+			ActionReply::Volume(state) => serde_json::to_value(state)?,
+			ActionReply::Brightness(state) => serde_json::to_value(state)?,
+			ActionReply::KeyLock(state) => serde_json::to_value(state)?,
+			ActionReply::Player(state) => serde_json::to_value(state)?,
+			ActionReply::Progress(fraction) => serde_json::to_value(ProgressState {
+				fraction: *fraction,
+			})?,
+			ActionReply::SegmentedProgress(value, n_segments) => {
+				serde_json::to_value(SegmentedProgressState {
+					value: *value,
+					n_segments: *n_segments,
+				})?
+			}
+			ActionReply::Inhibit(state) => serde_json::to_value(state)?,
+		};
+		Ok(Some(value))
+	}
+}
+
+/// A list of actions that should be activated in order.
+/// Modifier actions only apply to the actions of the same request.
+/// The reply of the last action that produced a state, or the first error,
+/// is sent back through `reply`
+pub struct ActionRequest {
+	pub actions: Vec<(ArgTypes, Option<String>)>,
+	pub reply: Option<Sender<Result<ActionReply, ActionError>>>,
+}
+
+impl ActionRequest {
+	/// Merges the next request into this one if both are the same action with the same
+	/// modifiers, so a burst of key repeats is displayed once. The steps of raise and lower
+	/// are summed, other values are replaced by the newer one.
+	/// Returns the reply of the merged request, or the request if it can't be merged
+	pub fn merge(
+		&mut self,
+		next: ActionRequest,
+	) -> Result<Option<Sender<Result<ActionReply, ActionError>>>, ActionRequest> {
+		let (Some(((arg_type, value), prefix)), Some(((next_type, next_value), next_prefix))) =
+			(self.actions.split_last(), next.actions.split_last())
+		else {
+			return Err(next);
+		};
+		// Other actions in the prefix would be skipped by merging
+		let mut context = ActionContext::default();
+		if prefix != next_prefix
+			|| arg_type != next_type
+			|| !prefix
+				.iter()
+				.all(|(arg_type, value)| context.set_modifier(arg_type, value))
+		{
+			return Err(next);
+		}
+
+		// Invalid steps aren't merged, so their error is still sent back
+		let merged = match arg_type {
+			ArgTypes::SinkVolumeRaise
+			| ArgTypes::SinkVolumeLower
+			| ArgTypes::SourceVolumeRaise
+			| ArgTypes::SourceVolumeLower => match (volume_step(value), volume_step(next_value)) {
+				(Some(step), Some(next_step)) => Some((step + next_step).to_string()),
+				_ => return Err(next),
+			},
+			ArgTypes::BrightnessRaise | ArgTypes::BrightnessLower => {
+				match (brightness_step(value), brightness_step(next_value)) {
+					(Some(step), Some(next_step)) => {
+						Some(step.saturating_add(next_step).min(100).to_string())
+					}
+					_ => return Err(next),
+				}
+			}
+			ArgTypes::BrightnessSet
+			| ArgTypes::CapsLock
+			| ArgTypes::NumLock
+			| ArgTypes::ScrollLock
+			| ArgTypes::KbdBacklight
+			| ArgTypes::CustomMessage
+			| ArgTypes::CustomProgress
+			| ArgTypes::CustomSegmentedProgress => next_value.clone(),
+			// Toggles and player commands have to be applied once per request
+			_ => return Err(next),
+		};
+		if let Some(last) = self.actions.last_mut() {
+			last.1 = merged;
+		}
+		Ok(next.reply)
+	}
+}
+
+/// The step of a volume raise or lower, None if it isn't valid
+fn volume_step(value: &Option<String>) -> Option<f64> {
+	match value.as_deref() {
+		None | Some("") => Some(VOLUME_CHANGE_DELTA),
+		Some(value) => value
+			.parse::<f64>()
+			.ok()
+			.filter(|step| step.is_finite() && *step >= 0.0),
+	}
+}
+
+/// The step of a brightness raise or lower in whole percents, None if it isn't valid
+fn brightness_step(value: &Option<String>) -> Option<u8> {
+	match value.as_deref() {
+		None | Some("") => Some(BRIGHTNESS_CHANGE_DELTA),
+		Some(value) => value.parse::<u8>().ok(),
+	}
+}
+
+/// Requests for the GTK Application that aren't actions
+pub enum AppRequest {
+	/// Rereads the config. The result is sent back through the sender
+	Reload(Sender<Result<(), ActionError>>),
+	/// Applies the changed settings to the displayed windows
+	RefreshSettings,
+	/// Hides or restores the persistent jobs after the inhibit state changed
+	RefreshInhibit,
+}
+
+/// Sends the actions to the GTK Application without waiting for the result
+pub async fn send_actions(
+	sender: &Sender<ActionRequest>,
+	actions: Vec<(ArgTypes, Option<String>)>,
+) -> fdo::Result<()> {
+	let request = ActionRequest {
+		actions,
+		reply: None,
+	};
+	match sender.send(request).await {
+		Ok(_) => Ok(()),
+		Err(error) => Err(fdo::Error::Failed(format!("Channel Send error: {}", error))),
+	}
+}
+
+/// Sends the actions to the GTK Application and waits for the resulting state
+pub async fn request_actions(
+	sender: &Sender<ActionRequest>,
+	actions: Vec<(ArgTypes, Option<String>)>,
+) -> Result<ActionReply, ActionError> {
+	let (reply_sender, reply_receiver) = async_channel::bounded(1);
+	let request = ActionRequest {
+		actions,
+		reply: Some(reply_sender),
+	};
+	if let Err(error) = sender.send(request).await {
+		return Err(ActionError::Failed(format!(
+			"Channel Send error: {}",
+			error
+		)));
+	}
+	match reply_receiver.recv().await {
+		Ok(result) => result,
+		Err(error) => Err(ActionError::Failed(format!(
+			"Channel Receive error: {}",
+			error
+		))),
+	}
+}
+
+/// Asks the GTK Application to reload the config and the user stylesheet
+pub async fn request_reload(sender: &Sender<AppRequest>) -> Result<(), ActionError> {
+	let (reply_sender, reply_receiver) = async_channel::bounded(1);
+	if let Err(error) = sender.send(AppRequest::Reload(reply_sender)).await {
+		return Err(ActionError::Failed(format!(
+			"Channel Send error: {}",
+			error
+		)));
+	}
+	match reply_receiver.recv().await {
+		Ok(result) => result,
+		Err(error) => Err(ActionError::Failed(format!(
+			"Channel Receive error: {}",
+			error
+		))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn request(actions: &[(ArgTypes, Option<&str>)]) -> ActionRequest {
+		ActionRequest {
+			actions: actions
+				.iter()
+				.map(|(arg_type, value)| (arg_type.clone(), value.map(str::to_owned)))
+				.collect(),
+			reply: None,
+		}
+	}
+
+	/// The actions of the merged request, None if they weren't merged
+	fn merge(
+		actions: &[(ArgTypes, Option<&str>)],
+		next_actions: &[(ArgTypes, Option<&str>)],
+	) -> Option<Vec<(ArgTypes, Option<String>)>> {
+		let mut first = request(actions);
+		first.merge(request(next_actions)).ok()?;
+		Some(first.actions)
+	}
+
+	#[test]
+	fn merge_sums_volume_steps() {
+		assert_eq!(
+			merge(
+				&[(ArgTypes::SinkVolumeRaise, Some("2"))],
+				&[(ArgTypes::SinkVolumeRaise, Some("3"))]
+			),
+			Some(vec![(ArgTypes::SinkVolumeRaise, Some("5".to_owned()))])
+		);
+		// Missing steps are the default step
+		assert_eq!(
+			merge(
+				&[(ArgTypes::SourceVolumeLower, None)],
+				&[(ArgTypes::SourceVolumeLower, Some("1.5"))]
+			),
+			Some(vec![(ArgTypes::SourceVolumeLower, Some("6.5".to_owned()))])
+		);
+	}
+
+	#[test]
+	fn merge_sums_brightness_steps_up_to_100() {
+		assert_eq!(
+			merge(
+				&[(ArgTypes::BrightnessRaise, None)],
+				&[(ArgTypes::BrightnessRaise, Some("10"))]
+			),
+			Some(vec![(ArgTypes::BrightnessRaise, Some("15".to_owned()))])
+		);
+		assert_eq!(
+			merge(
+				&[(ArgTypes::BrightnessLower, Some("80"))],
+				&[(ArgTypes::BrightnessLower, Some("80"))]
+			),
+			Some(vec![(ArgTypes::BrightnessLower, Some("100".to_owned()))])
+		);
+	}
+
+	#[test]
+	fn merge_keeps_the_newest_value_of_set_actions() {
+		assert_eq!(
+			merge(
+				&[(ArgTypes::BrightnessSet, Some("20"))],
+				&[(ArgTypes::BrightnessSet, Some("40"))]
+			),
+			Some(vec![(ArgTypes::BrightnessSet, Some("40".to_owned()))])
+		);
+		assert_eq!(
+			merge(
+				&[
+					(ArgTypes::MonitorName, Some("eDP-1")),
+					(ArgTypes::CustomProgress, Some("0.2"))
+				],
+				&[
+					(ArgTypes::MonitorName, Some("eDP-1")),
+					(ArgTypes::CustomProgress, Some("0.3"))
+				]
+			),
+			Some(vec![
+				(ArgTypes::MonitorName, Some("eDP-1".to_owned())),
+				(ArgTypes::CustomProgress, Some("0.3".to_owned()))
+			])
+		);
+	}
+
+	#[test]
+	fn merge_skips_different_actions_and_modifiers() {
+		assert!(merge(
+			&[(ArgTypes::SinkVolumeRaise, None)],
+			&[(ArgTypes::SinkVolumeLower, None)]
+		)
+		.is_none());
+		assert!(merge(
+			&[
+				(ArgTypes::DeviceName, Some("a")),
+				(ArgTypes::SinkVolumeRaise, None)
+			],
+			&[
+				(ArgTypes::DeviceName, Some("b")),
+				(ArgTypes::SinkVolumeRaise, None)
+			]
+		)
+		.is_none());
+		// Other actions in the request would be skipped
+		assert!(merge(
+			&[
+				(ArgTypes::SinkVolumeMuteToggle, None),
+				(ArgTypes::SinkVolumeRaise, None)
+			],
+			&[
+				(ArgTypes::SinkVolumeMuteToggle, None),
+				(ArgTypes::SinkVolumeRaise, None)
+			]
+		)
+		.is_none());
+		assert!(merge(&[], &[(ArgTypes::SinkVolumeRaise, None)]).is_none());
+	}
+
+	#[test]
+	fn merge_skips_toggles_and_invalid_steps() {
+		assert!(merge(
+			&[(ArgTypes::SinkVolumeMuteToggle, None)],
+			&[(ArgTypes::SinkVolumeMuteToggle, None)]
+		)
+		.is_none());
+		assert!(merge(
+			&[(ArgTypes::SinkVolumeRaise, Some("5"))],
+			&[(ArgTypes::SinkVolumeRaise, Some("loud"))]
+		)
+		.is_none());
+		assert!(merge(
+			&[(ArgTypes::BrightnessRaise, Some("-5"))],
+			&[(ArgTypes::BrightnessRaise, Some("5"))]
+		)
+		.is_none());
+	}
+}
@@ -1,36 +1,59 @@
-use crate::args::ArgsServer;
-use crate::argtypes::ArgTypes;
+use crate::actions::{ActionReply, ActionRequest, AppRequest};
+use crate::args::{ArgsServer, Renderer};
+use crate::argtypes::{ActionContext, ArgTypes};
 use crate::config::{self, APPLICATION_NAME, DBUS_BACKEND_NAME};
-use crate::global_utils::segmented_progress_parser;
-use crate::osd_window::SwayosdWindow;
+use crate::error::ActionError;
+use crate::global_utils::{progress_parser, segmented_progress_parser};
+use crate::osd_window::{
+	kbd_backlight_icon_name, keylock_label_and_icon_name, volume_icon_name, JobProgress,
+	ProgressJob, SwayosdWindow,
+};
+use crate::renderer::{LogRenderer, OsdRenderer};
+use crate::state::{InhibitState, KeyLockState, OsdEvent, PlayerState};
 use crate::utils::{self, *};
+use crate::volume_backend::{self, VolumeBackend};
 use crate::{login1, playerctl::*, upower};
 use async_channel::{Receiver, Sender};
 use async_std::stream::StreamExt;
-use gtk::gio::{DBusConnection, ListModel};
-use gtk::glib::ControlFlow;
+use gtk::gio::{DBusConnection, FileMonitor, FileMonitorEvent, ListModel};
+use gtk::glib::{user_config_dir, ControlFlow};
 use gtk::{
 	gdk,
 	gio::{
 		self, ApplicationFlags, BusNameWatcherFlags, BusType, DBusSignalFlags, SignalSubscriptionId,
 	},
-	glib::{clone, Char, ControlFlow::Break, MainContext, OptionArg, OptionFlags},
+	glib::{self, clone, Char, ControlFlow::Break, MainContext, OptionArg, OptionFlags},
 	prelude::*,
-	Application,
+	Application, CssProvider,
 };
-use pulsectl::controllers::{SinkController, SourceController};
 use std::cell::RefCell;
+use std::collections::{hash_map::Entry, HashMap};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
-use super::config::user::ServerConfig;
+use super::config::user::{find_user_config, ServerConfig};
 
 #[derive(Clone, Shrinkwrap)]
 pub struct SwayOSDApplication {
 	#[shrinkwrap(main_field)]
 	app: gtk::Application,
-	windows: Rc<RefCell<Vec<SwayosdWindow>>>,
+	/// A window per monitor, or a single headless renderer
+	windows: Rc<RefCell<Vec<Rc<dyn OsdRenderer>>>>,
+	/// The custom progresses sent with an ID, the most recently updated last
+	jobs: Rc<RefCell<Vec<ProgressJob>>>,
 	activated: Rc<RefCell<bool>>,
+	/// Connected on the first volume action of each device type
+	volume_backends: Rc<RefCell<HashMap<VolumeDeviceType, Box<dyn VolumeBackend>>>>,
+	osd_sender: Sender<OsdEvent>,
+	/// Notifies the D-Bus server that the settings properties changed
+	settings_sender: Sender<()>,
+	/// Replaced when the config is reloaded
+	server_config: Rc<RefCell<Arc<ServerConfig>>>,
+	args: Arc<ArgsServer>,
+	/// None without GTK
+	user_provider: Option<CssProvider>,
+	file_monitors: Rc<RefCell<Vec<FileMonitor>>>,
 	_hold: Rc<gio::ApplicationHoldGuard>,
 }
 
@@ -38,7 +61,10 @@ impl SwayOSDApplication {
 	pub fn new(
 		server_config: Arc<ServerConfig>,
 		args: Arc<ArgsServer>,
-		action_receiver: Receiver<(ArgTypes, String)>,
+		action_receiver: Receiver<ActionRequest>,
+		app_receiver: Receiver<AppRequest>,
+		osd_sender: Sender<OsdEvent>,
+		settings_sender: Sender<()>,
 	) -> Self {
 		let app = Application::new(Some(APPLICATION_NAME), ApplicationFlags::FLAGS_NONE);
 		let hold = Rc::new(app.hold());
@@ -55,52 +81,133 @@ impl SwayOSDApplication {
 			Some("<from 0.0 to 1.0>"),
 		);
 
+		// The user CSS theme, loaded in apply_config
+		let user_provider = (args.renderer == Renderer::Gtk).then(|| {
+			let user_provider = CssProvider::new();
+			user_provider.connect_parsing_error(|_provider, _section, error| {
+				eprintln!("Failed loading user defined style.css: {}", error);
+			});
+			gtk::style_context_add_provider_for_display(
+				&gdk::Display::default().expect("Failed getting the default screen"),
+				&user_provider,
+				gtk::STYLE_PROVIDER_PRIORITY_USER,
+			);
+			user_provider
+		});
+
 		let osd_app = SwayOSDApplication {
 			app: app.clone(),
 			windows: Rc::new(RefCell::new(Vec::new())),
+			jobs: Rc::new(RefCell::new(Vec::new())),
 			activated: Rc::new(RefCell::new(false)),
+			volume_backends: Rc::new(RefCell::new(HashMap::new())),
+			osd_sender,
+			settings_sender,
+			server_config: Rc::new(RefCell::new(server_config.clone())),
+			args,
+			user_provider,
+			file_monitors: Rc::new(RefCell::new(Vec::new())),
 			_hold: hold,
 		};
 
 		// Apply Server Config
-		if let Some(margin) = server_config.top_margin
-			&& (0_f32..1_f32).contains(&margin)
-		{
-			set_top_margin(margin);
-		}
-		if let Some(max_volume) = server_config.max_volume {
-			set_default_max_volume(max_volume);
-			reset_max_volume();
-		}
-		if let Some(min_brightness) = server_config.min_brightness {
-			set_default_min_brightness(min_brightness);
-			reset_min_brightness();
-		}
-		if let Some(show) = server_config.show_percentage {
-			set_show_percentage(show);
-		}
-
-		Self::parse_args(&args);
+		osd_app.apply_config(&server_config);
 
 		// Listen for any actions sent from swayosd-client
 		MainContext::default().spawn_local(clone!(
 			#[strong]
 			osd_app,
+			async move {
+				// A request that couldn't be merged into the previous one
+				let mut pending: Option<ActionRequest> = None;
+				loop {
+					let mut request = match pending.take() {
+						Some(request) => request,
+						None => match action_receiver.recv().await {
+							Ok(request) => request,
+							Err(_) => break,
+						},
+					};
+					// Coalesce the requests that queued up while the previous one was displayed
+					let mut replies: Vec<_> = request.reply.take().into_iter().collect();
+					while let Ok(next) = action_receiver.try_recv() {
+						match request.merge(next) {
+							Ok(reply) => replies.extend(reply),
+							Err(next) => {
+								pending = Some(next);
+								break;
+							}
+						}
+					}
+
+					let mut context = ActionContext::default();
+					let mut result = Ok(ActionReply::None);
+					for (arg_type, data) in request.actions {
+						if context.set_modifier(&arg_type, &data) {
+							continue;
+						}
+						let server_config = osd_app.server_config.borrow().clone();
+						match osd_app.action_activated(server_config, &context, arg_type, data) {
+							Ok(ActionReply::None) => (),
+							Ok(reply) => result = Ok(reply),
+							Err(error) => {
+								eprintln!("Could not activate action: {:?}", error);
+								result = Err(error);
+								break;
+							}
+						}
+					}
+					for reply in replies {
+						if let Err(error) = reply.send(result.clone()).await {
+							eprintln!("Channel Send error: {}", error);
+						}
+					}
+				}
+				Break
+			}
+		));
+
+		// Handle the requests sent through D-Bus that aren't actions
+		MainContext::default().spawn_local(clone!(
 			#[strong]
-			server_config,
+			osd_app,
 			async move {
-				while let Ok((arg_type, data)) = action_receiver.recv().await {
-					if let Err(error) = osd_app.action_activated(
-						server_config.clone(),
-						arg_type,
-						(!data.is_empty()).then_some(data),
-					) {
-						eprintln!("Could not activate action: {:?}", error)
+				while let Ok(request) = app_receiver.recv().await {
+					match request {
+						AppRequest::Reload(reply) => {
+							if let Err(error) = reply.send(osd_app.reload()).await {
+								eprintln!("Channel Send error: {}", error);
+							}
+						}
+						AppRequest::RefreshSettings => {
+							for window in osd_app.windows.borrow().iter() {
+								window.refresh_settings();
+							}
+						}
+						AppRequest::RefreshInhibit => osd_app.update_persistent_jobs(),
 					}
 				}
 				Break
 			}
 		));
+		// Reload the config on SIGHUP
+		glib::unix_signal_add_local(
+			libc::SIGHUP,
+			clone!(
+				#[strong]
+				osd_app,
+				move || {
+					if let Err(error) = osd_app.reload() {
+						eprintln!("Could not reload the config: {}", error);
+					}
+					ControlFlow::Continue
+				}
+			),
+		);
+		// Reload the config when config.toml or style.css change
+		if server_config.watch_config.unwrap_or(false) {
+			osd_app.watch_config_files(&server_config);
+		}
 
 		// Listen for UPower keyboard backlight changes
 		if server_config.keyboard_backlight.unwrap_or(true) {
@@ -113,7 +220,7 @@ impl SwayOSDApplication {
 			));
 		}
 
-		let (sender, receiver) = async_channel::bounded::<(u16, i32)>(1);
+		let (sender, receiver) = async_channel::bounded::<(u16, i32, String)>(1);
 		// Listen to the LibInput Backend and activate the Application action
 		MainContext::default().spawn_local(clone!(
 			#[strong]
@@ -121,7 +228,22 @@ impl SwayOSDApplication {
 			#[strong]
 			server_config,
 			async move {
-				while let Ok((key_code, state)) = receiver.recv().await {
+				// Only react to the keyboards of our own seat
+				let own_seat = match login1::Login1::own_seat().await {
+					Ok(seat) => seat,
+					Err(error) => {
+						eprintln!("Could not find the seat of the session: {}", error);
+						None
+					}
+				};
+				while let Ok((key_code, state, seat)) = receiver.recv().await {
+					// Older backends don't send the seat
+					if let Some(own_seat) = &own_seat
+						&& !seat.is_empty()
+						&& seat != *own_seat
+					{
+						continue;
+					}
 					let (arg_type, data): (ArgTypes, Option<String>) =
 						match evdev_rs::enums::int_to_ev_key(key_code as u32) {
 							Some(evdev_rs::enums::EV_KEY::KEY_CAPSLOCK) => {
@@ -135,9 +257,12 @@ impl SwayOSDApplication {
 							}
 							_ => continue,
 						};
-					if let Err(error) =
-						osd_app.action_activated(server_config.clone(), arg_type, data)
-					{
+					if let Err(error) = osd_app.action_activated(
+						server_config.clone(),
+						&ActionContext::default(),
+						arg_type,
+						data,
+					) {
 						eprintln!("Could not activate action: {:?}", error)
 					}
 				}
@@ -169,11 +294,112 @@ impl SwayOSDApplication {
 		osd_app
 	}
 
+	/// Applies the config to the existing and future windows.
+	/// The command line arguments take precedence
+	fn apply_config(&self, server_config: &ServerConfig) {
+		set_top_margin(
+			server_config
+				.top_margin
+				.filter(|margin| TOP_MARGIN_RANGE.contains(margin))
+				.unwrap_or(*TOP_MARGIN_DEFAULT),
+		);
+		set_default_max_volume(server_config.max_volume.unwrap_or(PRIV_MAX_VOLUME_DEFAULT));
+		set_default_min_brightness(
+			server_config
+				.min_brightness
+				.unwrap_or(PRIV_MIN_BRIGHTNESS_DEFAULT),
+		);
+		set_show_percentage(server_config.show_percentage.unwrap_or(false));
+		set_audio_backend(server_config.audio_backend.unwrap_or_default());
+		// Reconnect with the configured audio backend
+		self.volume_backends.borrow_mut().clear();
+		set_inhibit_shown_kinds(
+			server_config
+				.inhibit_shown_kinds
+				.clone()
+				.unwrap_or_default(),
+		);
+
+		Self::parse_args(&self.args);
+
+		// Try loading the users CSS theme
+		if let Some(user_provider) = &self.user_provider {
+			match user_style_path(self.args.style.clone().or(server_config.style.clone())) {
+				Some(path) => {
+					user_provider.load_from_path(&path);
+					println!("Loaded user defined CSS file");
+				}
+				None => user_provider.load_from_data(""),
+			}
+		}
+
+		for window in self.windows.borrow().iter() {
+			window.refresh_settings();
+		}
+	}
+
+	/// Rereads the config file and the user stylesheet.
+	/// Only the values used by the windows and actions are applied, the rest needs a restart
+	pub fn reload(&self) -> Result<(), ActionError> {
+		let server_config = match config::user::read_user_config(self.args.config.as_deref()) {
+			Ok(config) => config.server,
+			Err(error) => {
+				return Err(ActionError::Failed(format!(
+					"Failed to parse config file: {}",
+					error
+				)));
+			}
+		};
+		self.apply_config(&server_config);
+		self.server_config.replace(Arc::new(server_config));
+		if let Err(error) = self.settings_sender.try_send(()) {
+			eprintln!("Channel Send error: {}", error);
+		}
+		println!("Reloaded the config");
+		Ok(())
+	}
+
+	fn watch_config_files(&self, server_config: &ServerConfig) {
+		let user_dir = user_config_dir().join("swayosd");
+		let config_path = self
+			.args
+			.config
+			.clone()
+			.or_else(find_user_config)
+			.unwrap_or_else(|| user_dir.join("config.toml"));
+		let style_path = user_style_path(self.args.style.clone().or(server_config.style.clone()))
+			.map(PathBuf::from)
+			.unwrap_or_else(|| user_dir.join("style.css"));
+
+		for path in [config_path, style_path] {
+			let monitor = match gio::File::for_path(&path)
+				.monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+			{
+				Ok(monitor) => monitor,
+				Err(error) => {
+					eprintln!("Could not watch {}: {}", path.display(), error);
+					continue;
+				}
+			};
+			let osd_app = self.clone();
+			monitor.connect_changed(move |_, _, _, event| {
+				if matches!(
+					event,
+					FileMonitorEvent::ChangesDoneHint | FileMonitorEvent::Created
+				) && let Err(error) = osd_app.reload()
+				{
+					eprintln!("Could not reload the config: {}", error);
+				}
+			});
+			self.file_monitors.borrow_mut().push(monitor);
+		}
+	}
+
 	fn parse_args(args: &ArgsServer) {
 		// Top Margin
 		if let Some(value) = args.top_margin.to_owned() {
 			match value.parse::<f32>() {
-				Ok(top_margin @ 0.0f32..=1.0f32) => {
+				Ok(top_margin) if TOP_MARGIN_RANGE.contains(&top_margin) => {
 					set_top_margin(top_margin);
 				}
 				_ => {
@@ -200,6 +426,7 @@ impl SwayOSDApplication {
 				}
 				if let Err(error) = self.action_activated(
 					server_config.clone(),
+					&ActionContext::default(),
 					ArgTypes::KbdBacklight,
 					Some(format!("{}:{}", args.value, max_brightness)),
 				) {
@@ -214,7 +441,7 @@ impl SwayOSDApplication {
 	}
 
 	fn libinput_backend_appeared(
-		sender: &Sender<(u16, i32)>,
+		sender: &Sender<(u16, i32, String)>,
 		signal_id: &Arc<Mutex<Option<SignalSubscriptionId>>>,
 		connection: DBusConnection,
 	) {
@@ -239,13 +466,18 @@ impl SwayOSDApplication {
 				move |_, _, _, _, _, variant| {
 					let key_code = variant.try_child_get::<u16>(0);
 					let state = variant.try_child_get::<i32>(1);
+					let seat = variant
+						.try_child_get::<String>(2)
+						.ok()
+						.flatten()
+						.unwrap_or_default();
 					match (key_code, state) {
 						(Ok(Some(key_code)), Ok(Some(state))) => {
 							MainContext::default().spawn_local(clone!(
 								#[strong]
 								sender,
 								async move {
-									if let Err(error) = sender.send((key_code, state)).await {
+									if let Err(error) = sender.send((key_code, state, seat)).await {
 										eprintln!("Channel Send error: {}", error);
 									}
 								}
@@ -297,7 +529,19 @@ impl SwayOSDApplication {
 		zbus::Result::Ok(Break)
 	}
 
-	pub fn start(&self) -> i32 {
+	/// Runs the application. Sends to the ready sender once the windows exist
+	pub fn start(&self, ready_sender: Sender<()>) -> i32 {
+		// Print the OSDs without opening windows or initializing GTK
+		if self.args.renderer != Renderer::Gtk {
+			let renderer = LogRenderer::new(self.args.renderer == Renderer::Json);
+			self.windows.borrow_mut().push(Rc::new(renderer));
+			if let Err(error) = ready_sender.try_send(()) {
+				eprintln!("Channel Send error: {}", error);
+			}
+			glib::MainLoop::new(None, false).run();
+			return 0;
+		}
+
 		let osd_app = self.clone();
 		self.app.connect_activate(move |_| {
 			if let Ok(mut is_activated) = osd_app.activated.try_borrow_mut() {
@@ -306,6 +550,9 @@ impl SwayOSDApplication {
 				}
 				*is_activated = true;
 				osd_app.initialize();
+				if let Err(error) = ready_sender.try_send(()) {
+					eprintln!("Channel Send error: {}", error);
+				}
 			}
 		});
 
@@ -320,6 +567,8 @@ impl SwayOSDApplication {
 		self.app.run_with_args(&empty_args).into()
 	}
 
+	/// Creates a window per monitor. Everything that needs GTK or GDK starts here,
+	/// the headless renderers never call it
 	fn initialize(&self) {
 		let display: gdk::Display = gdk::Display::default().expect("Could not get GDK Display!");
 		let monitors = display.monitors();
@@ -360,20 +609,32 @@ impl SwayOSDApplication {
 				.and_then(|obj| obj.downcast::<gdk::Monitor>().ok())
 			{
 				let window = SwayosdWindow::new(&self.app, &monitor);
-				windows.push(window);
+				windows.push(Rc::new(window));
 			}
 		}
+		drop(windows);
+
+		// Display the persistent jobs on the new windows
+		if added > 0 {
+			self.update_persistent_jobs();
+		}
+	}
+
+	/// The windows that display the OSD of the kind, none while it's inhibited
+	fn osd_windows(&self, kind: &str, monitor_name: Option<&str>) -> Vec<Rc<dyn OsdRenderer>> {
+		if is_osd_inhibited(kind) {
+			return Vec::new();
+		}
+		self.choose_windows(monitor_name)
 	}
 
-	fn choose_windows(&self) -> Vec<SwayosdWindow> {
+	fn choose_windows(&self, monitor_name: Option<&str>) -> Vec<Rc<dyn OsdRenderer>> {
 		let mut selected_windows = Vec::new();
 
-		match get_monitor_name() {
+		match monitor_name {
 			Some(monitor_name) => {
 				for window in self.windows.borrow().to_owned() {
-					if let Some(monitor_connector) = window.monitor.connector()
-						&& monitor_name == monitor_connector
-					{
+					if window.matches_monitor(monitor_name) {
 						selected_windows.push(window);
 					}
 				}
@@ -392,115 +653,56 @@ impl SwayOSDApplication {
 	fn action_activated(
 		&self,
 		server_config: Arc<ServerConfig>,
+		context: &ActionContext,
 		arg_type: ArgTypes,
 		value: Option<String>,
-	) -> Result<(), Box<dyn std::error::Error>> {
-		match (arg_type, value) {
-			(ArgTypes::SinkVolumeRaise, step) => {
-				let mut device_type = VolumeDeviceType::Sink(SinkController::create()?);
-				if let Some(device) =
-					change_device_volume(&mut device_type, VolumeChangeType::Raise, step)
-				{
-					for window in self.choose_windows() {
-						window.changed_volume(&device, &device_type);
-					}
-				}
-				reset_max_volume();
-				reset_device_name();
-				reset_monitor_name();
-			}
-			(ArgTypes::SinkVolumeLower, step) => {
-				let mut device_type = VolumeDeviceType::Sink(SinkController::create()?);
-				if let Some(device) =
-					change_device_volume(&mut device_type, VolumeChangeType::Lower, step)
-				{
-					for window in self.choose_windows() {
-						window.changed_volume(&device, &device_type);
-					}
-				}
-				reset_max_volume();
-				reset_device_name();
-				reset_monitor_name();
-			}
-			(ArgTypes::SinkVolumeMuteToggle, _) => {
-				let mut device_type = VolumeDeviceType::Sink(SinkController::create()?);
-				if let Some(device) =
-					change_device_volume(&mut device_type, VolumeChangeType::MuteToggle, None)
-				{
-					for window in self.choose_windows() {
-						window.changed_volume(&device, &device_type);
-					}
-				}
-				reset_max_volume();
-				reset_device_name();
-				reset_monitor_name();
-			}
-			(ArgTypes::SourceVolumeRaise, step) => {
-				let mut device_type = VolumeDeviceType::Source(SourceController::create()?);
-				if let Some(device) =
-					change_device_volume(&mut device_type, VolumeChangeType::Raise, step)
-				{
-					for window in self.choose_windows() {
-						window.changed_volume(&device, &device_type);
-					}
-				}
-				reset_max_volume();
-				reset_device_name();
-				reset_monitor_name();
-			}
-			(ArgTypes::SourceVolumeLower, step) => {
-				let mut device_type = VolumeDeviceType::Source(SourceController::create()?);
-				if let Some(device) =
-					change_device_volume(&mut device_type, VolumeChangeType::Lower, step)
-				{
-					for window in self.choose_windows() {
-						window.changed_volume(&device, &device_type);
-					}
-				}
-				reset_max_volume();
-				reset_device_name();
-				reset_monitor_name();
-			}
-			(ArgTypes::SourceVolumeMuteToggle, _) => {
-				let mut device_type = VolumeDeviceType::Source(SourceController::create()?);
-				if let Some(device) =
-					change_device_volume(&mut device_type, VolumeChangeType::MuteToggle, None)
-				{
-					for window in self.choose_windows() {
-						window.changed_volume(&device, &device_type);
-					}
-				}
-				reset_max_volume();
-				reset_device_name();
-				reset_monitor_name();
-			}
+	) -> Result<ActionReply, ActionError> {
+		let reply = match (arg_type, value) {
+			(ArgTypes::SinkVolumeRaise, step) => self.volume_action(
+				context,
+				VolumeDeviceType::Sink,
+				VolumeChangeType::Raise,
+				step,
+			)?,
+			(ArgTypes::SinkVolumeLower, step) => self.volume_action(
+				context,
+				VolumeDeviceType::Sink,
+				VolumeChangeType::Lower,
+				step,
+			)?,
+			(ArgTypes::SinkVolumeMuteToggle, _) => self.volume_action(
+				context,
+				VolumeDeviceType::Sink,
+				VolumeChangeType::MuteToggle,
+				None,
+			)?,
+			(ArgTypes::SourceVolumeRaise, step) => self.volume_action(
+				context,
+				VolumeDeviceType::Source,
+				VolumeChangeType::Raise,
+				step,
+			)?,
+			(ArgTypes::SourceVolumeLower, step) => self.volume_action(
+				context,
+				VolumeDeviceType::Source,
+				VolumeChangeType::Lower,
+				step,
+			)?,
+			(ArgTypes::SourceVolumeMuteToggle, _) => self.volume_action(
+				context,
+				VolumeDeviceType::Source,
+				VolumeChangeType::MuteToggle,
+				None,
+			)?,
 			// TODO: Brightness
 			(ArgTypes::BrightnessRaise, step) => {
-				let mut brightness_backend = change_brightness(BrightnessChangeType::Raise, step)?;
-				for window in self.choose_windows() {
-					window.changed_brightness(brightness_backend.as_mut());
-				}
-				reset_min_brightness();
-				reset_monitor_name();
+				self.brightness_action(context, BrightnessChangeType::Raise, step)?
 			}
 			(ArgTypes::BrightnessLower, step) => {
-				if let Ok(mut brightness_backend) =
-					change_brightness(BrightnessChangeType::Lower, step)
-				{
-					for window in self.choose_windows() {
-						window.changed_brightness(brightness_backend.as_mut());
-					}
-				}
-				reset_min_brightness();
-				reset_monitor_name();
+				self.brightness_action(context, BrightnessChangeType::Lower, step)?
 			}
 			(ArgTypes::BrightnessSet, value) => {
-				let mut brightness_backend = change_brightness(BrightnessChangeType::Set, value)?;
-				for window in self.choose_windows() {
-					window.changed_brightness(brightness_backend.as_mut());
-				}
-				reset_min_brightness();
-				reset_monitor_name();
+				self.brightness_action(context, BrightnessChangeType::Set, value)?
 			}
 			(ArgTypes::CapsLock, value) => {
 				let i32_value = value.clone().unwrap_or("-1".to_owned());
@@ -508,10 +710,10 @@ impl SwayOSDApplication {
 					Ok(value) if (0..=1).contains(&value) => value == 1,
 					_ => get_key_lock_state(KeysLocks::CapsLock, value),
 				};
-				for window in self.choose_windows() {
+				for window in self.osd_windows("caps-lock", context.monitor_name.as_deref()) {
 					window.changed_keylock(KeysLocks::CapsLock, state)
 				}
-				reset_monitor_name();
+				self.keylock_shown(context, KeysLocks::CapsLock, "caps-lock", state)
 			}
 			(ArgTypes::NumLock, value) => {
 				let i32_value = value.clone().unwrap_or("-1".to_owned());
@@ -519,10 +721,10 @@ impl SwayOSDApplication {
 					Ok(value) if (0..=1).contains(&value) => value == 1,
 					_ => get_key_lock_state(KeysLocks::NumLock, value),
 				};
-				for window in self.choose_windows() {
+				for window in self.osd_windows("num-lock", context.monitor_name.as_deref()) {
 					window.changed_keylock(KeysLocks::NumLock, state)
 				}
-				reset_monitor_name();
+				self.keylock_shown(context, KeysLocks::NumLock, "num-lock", state)
 			}
 			(ArgTypes::ScrollLock, value) => {
 				let i32_value = value.clone().unwrap_or("-1".to_owned());
@@ -530,124 +732,384 @@ impl SwayOSDApplication {
 					Ok(value) if (0..=1).contains(&value) => value == 1,
 					_ => get_key_lock_state(KeysLocks::ScrollLock, value),
 				};
-				for window in self.choose_windows() {
+				for window in self.osd_windows("scroll-lock", context.monitor_name.as_deref()) {
 					window.changed_keylock(KeysLocks::ScrollLock, state)
 				}
-				reset_monitor_name();
+				self.keylock_shown(context, KeysLocks::ScrollLock, "scroll-lock", state)
 			}
-			(ArgTypes::MaxVolume, max) => {
-				let volume: u8 = match max {
-					Some(max) => match max.parse() {
-						Ok(max) => max,
-						_ => get_default_max_volume(),
-					},
-					_ => get_default_max_volume(),
-				};
-				set_max_volume(volume)
-			}
-			(ArgTypes::MinBrightness, min) => {
-				let brightness: u32 = match min {
-					Some(min) => match min.parse() {
-						Ok(min) => min,
-						_ => get_default_min_brightness(),
-					},
-					_ => get_default_min_brightness(),
-				};
-				set_min_brightness(brightness)
-			}
-			(ArgTypes::Player, name) => set_player(name.unwrap_or("".to_string())),
 			(ArgTypes::Playerctl, value) => {
 				let value = &value.unwrap_or("".to_string());
-				let action = PlayerctlAction::from(value)?;
-				if let Ok(mut player) = Playerctl::new(action, server_config) {
-					match player.run() {
-						Ok(_) => {
-							let (icon, label) = (player.icon.unwrap_or_default(), &player.label);
-							for window in self.choose_windows() {
-								window.changed_player(&icon, label.as_deref())
-							}
-							reset_monitor_name();
-						}
-						Err(x) => {
-							eprintln!("couldn't run player change: \"{:?}\"!", x)
-						}
-					}
-				} else {
-					eprintln!("Unable to get players! are any opened?")
+				let action = PlayerctlAction::from(value).map_err(ActionError::InvalidValue)?;
+				let player = PlayerctlDeviceRaw::from(context.player.clone().unwrap_or_default())
+					.unwrap_or(PlayerctlDeviceRaw::None);
+				let mut player = Playerctl::new(action, player, server_config)
+					.map_err(|error| ActionError::NoPlayer(error.to_string()))?;
+				if let Err(error) = player.run() {
+					return Err(ActionError::Failed(format!(
+						"couldn't run player change: \"{:?}\"!",
+						error
+					)));
 				}
-
-				reset_player();
+				let (icon, label) = (player.icon.unwrap_or_default(), &player.label);
+				for window in self.osd_windows("player", context.monitor_name.as_deref()) {
+					window.changed_player(&icon, label.as_deref())
+				}
+				self.osd_shown(
+					context,
+					OsdEvent {
+						kind: "player".to_owned(),
+						label: label.clone().unwrap_or_default(),
+						icon: icon.clone(),
+						device: player.player_name.clone().unwrap_or_default(),
+						..Default::default()
+					},
+				);
+				ActionReply::Player(PlayerState {
+					player: player.player_name.unwrap_or_default(),
+					status: player.status.unwrap_or_default(),
+					icon,
+					label: label.clone().unwrap_or_default(),
+				})
 			}
 			(ArgTypes::KbdBacklight, values) => {
 				if let Some(values) = values
 					&& let Ok((value, n_segments)) = segmented_progress_parser(&values)
 				{
-					for window in self.choose_windows() {
+					for window in self.osd_windows("kbd-backlight", context.monitor_name.as_deref())
+					{
 						window.changed_kbd_backlight(value, n_segments);
 					}
+					self.osd_shown(
+						context,
+						OsdEvent {
+							kind: "kbd-backlight".to_owned(),
+							value: value.min(n_segments) as f64,
+							max: n_segments as f64,
+							icon: kbd_backlight_icon_name(value, n_segments).to_owned(),
+							..Default::default()
+						},
+					);
 				}
-				reset_monitor_name();
-			}
-			(ArgTypes::DeviceName, name) => {
-				set_device_name(name.unwrap_or(DEVICE_NAME_DEFAULT.to_string()))
-			}
-			(ArgTypes::MonitorName, name) => {
-				if let Some(name) = name {
-					set_monitor_name(name)
-				}
+				ActionReply::None
 			}
 			(ArgTypes::CustomMessage, message) => {
 				if let Some(message) = message {
-					for window in self.choose_windows() {
-						window.custom_message(message.as_str(), get_icon_name().as_deref());
+					for window in
+						self.osd_windows("custom-message", context.monitor_name.as_deref())
+					{
+						window.custom_message(message.as_str(), context.icon_name.as_deref());
 					}
+					self.osd_shown(
+						context,
+						OsdEvent {
+							kind: "custom-message".to_owned(),
+							label: message,
+							icon: context.icon_name.clone().unwrap_or_default(),
+							..Default::default()
+						},
+					);
 				}
-				reset_icon_name();
-				reset_monitor_name();
+				ActionReply::None
 			}
 			(ArgTypes::CustomProgress, fraction) => {
+				let mut reply = ActionReply::None;
 				if let Some(fraction) = fraction {
-					let fraction: f64 = fraction.parse::<f64>().unwrap_or(1.0);
-					for window in self.choose_windows() {
-						window.custom_progress(
-							fraction,
-							get_progress_text(),
-							get_icon_name().as_deref(),
-						);
-					}
+					let fraction = progress_parser(&fraction).map_err(ActionError::InvalidValue)?;
+					let (text, icon) =
+						self.show_custom_progress(context, JobProgress::Fraction(fraction));
+					self.osd_shown(
+						context,
+						OsdEvent {
+							kind: "custom-progress".to_owned(),
+							value: fraction.clamp(0.0, 1.0),
+							max: 1.0,
+							label: text.unwrap_or_default(),
+							icon: icon.unwrap_or_default(),
+							..Default::default()
+						},
+					);
+					reply = ActionReply::Progress(fraction.clamp(0.0, 1.0));
 				}
-				reset_progress_text();
-				reset_icon_name();
-				reset_monitor_name();
+				reply
 			}
 			(ArgTypes::CustomSegmentedProgress, values) => {
-				if let Some(values) = values
-					&& let Ok((value, n_segments)) = segmented_progress_parser(&values)
-				{
-					for window in self.choose_windows() {
-						window.custom_segmented_progress(
-							value,
-							n_segments,
-							get_progress_text(),
-							get_icon_name().as_deref(),
-						);
+				let (value, n_segments) = segmented_progress_parser(&values.unwrap_or_default())
+					.map_err(ActionError::InvalidValue)?;
+				let (text, icon) =
+					self.show_custom_progress(context, JobProgress::Segmented(value, n_segments));
+				self.osd_shown(
+					context,
+					OsdEvent {
+						kind: "custom-segmented-progress".to_owned(),
+						value: value.min(n_segments) as f64,
+						max: n_segments as f64,
+						label: text.unwrap_or_default(),
+						icon: icon.unwrap_or_default(),
+						..Default::default()
+					},
+				);
+				ActionReply::SegmentedProgress(value.min(n_segments), n_segments)
+			}
+			(ArgTypes::Inhibit, mode) => {
+				let inhibited = match mode.as_deref() {
+					None => get_inhibited(),
+					Some("on") => true,
+					Some("off") => false,
+					Some("toggle") => !get_inhibited(),
+					Some(mode) => {
+						return Err(ActionError::InvalidValue(format!(
+							"Unknown inhibit mode: \"{}\"",
+							mode
+						)));
 					}
+				};
+				set_inhibited(inhibited);
+				if let Some(kinds) = &context.inhibit_shown_kinds {
+					set_inhibit_shown_kinds(kinds.clone());
 				}
-				reset_progress_text();
-				reset_icon_name();
-				reset_monitor_name();
+				// Hide or restore the persistent jobs
+				self.update_persistent_jobs();
+				if let Err(error) = self.settings_sender.try_send(()) {
+					eprintln!("Channel Send error: {}", error);
+				}
+				ActionReply::Inhibit(InhibitState {
+					inhibited,
+					shown_kinds: get_inhibit_shown_kinds(),
+				})
 			}
-			(ArgTypes::CustomProgressText, text) => set_progress_text(text),
-			(ArgTypes::CustomIcon, icon) => {
-				set_icon_name(icon.unwrap_or(ICON_NAME_DEFAULT.to_string()))
+			(ArgTypes::CloseProgress, Some(id)) => {
+				// Closing a job that doesn't exist (anymore) isn't an error
+				self.jobs.borrow_mut().retain(|job| job.id != id);
+				for window in self.windows.borrow().iter() {
+					window.close_job(&id);
+				}
+				self.update_persistent_jobs();
+				ActionReply::None
 			}
 			(arg_type, data) => {
-				eprintln!(
+				return Err(ActionError::InvalidValue(format!(
 					"Failed to parse command... Type: {:?}, Data: {:?}",
 					arg_type, data
-				)
+				)));
 			}
 		};
-		Ok(())
+		Ok(reply)
+	}
+
+	/// Displays the custom progress, as a tracked job if the request has a progress ID.
+	/// A job keeps the text, icon and monitor of its previous updates.
+	/// Returns the displayed text and icon name
+	fn show_custom_progress(
+		&self,
+		context: &ActionContext,
+		progress: JobProgress,
+	) -> (Option<String>, Option<String>) {
+		let kind = progress.kind();
+		let Some(id) = context.progress_id.clone() else {
+			for window in self.osd_windows(kind, context.monitor_name.as_deref()) {
+				match progress {
+					JobProgress::Fraction(fraction) => window.custom_progress(
+						fraction,
+						context.progress_text.clone(),
+						context.icon_name.as_deref(),
+					),
+					JobProgress::Segmented(value, n_segments) => window.custom_segmented_progress(
+						value,
+						n_segments,
+						context.progress_text.clone(),
+						context.icon_name.as_deref(),
+					),
+				}
+			}
+			return (context.progress_text.clone(), context.icon_name.clone());
+		};
+
+		let job = {
+			let mut jobs = self.jobs.borrow_mut();
+			let previous = jobs
+				.iter()
+				.position(|job| job.id == id)
+				.map(|index| jobs.remove(index));
+			let previous = previous.as_ref();
+			let job = ProgressJob {
+				id,
+				progress,
+				text: context
+					.progress_text
+					.clone()
+					.or_else(|| previous.and_then(|job| job.text.clone())),
+				icon_name: context
+					.icon_name
+					.clone()
+					.or_else(|| previous.and_then(|job| job.icon_name.clone())),
+				monitor_name: context
+					.monitor_name
+					.clone()
+					.or_else(|| previous.and_then(|job| job.monitor_name.clone())),
+				persistent: context.persistent || previous.is_some_and(|job| job.persistent),
+			};
+			jobs.push(job.clone());
+			job
+		};
+		for window in self.osd_windows(kind, job.monitor_name.as_deref()) {
+			window.show_job(&job);
+		}
+		self.update_persistent_jobs();
+		(job.text, job.icon_name)
+	}
+
+	/// Gives every window the most recently updated persistent job shown on it.
+	/// Inhibited jobs are kept but not displayed
+	fn update_persistent_jobs(&self) {
+		let windows = self.windows.borrow().clone();
+		let mut persistent_jobs: Vec<Option<ProgressJob>> = vec![None; windows.len()];
+		let jobs = self.jobs.borrow();
+		let shown_jobs = jobs
+			.iter()
+			.filter(|job| job.persistent && !is_osd_inhibited(job.progress.kind()));
+		for job in shown_jobs {
+			for window in self.choose_windows(job.monitor_name.as_deref()) {
+				if let Some(index) = windows.iter().position(|other| Rc::ptr_eq(other, &window)) {
+					persistent_jobs[index] = Some(job.clone());
+				}
+			}
+		}
+		for (window, job) in windows.iter().zip(persistent_jobs) {
+			window.set_persistent_job(job);
+		}
+	}
+
+	fn volume_action(
+		&self,
+		context: &ActionContext,
+		device_type: VolumeDeviceType,
+		change_type: VolumeChangeType,
+		step: Option<String>,
+	) -> Result<ActionReply, ActionError> {
+		let max_volume = context.max_volume.unwrap_or_else(get_default_max_volume);
+		let mut backends = self.volume_backends.borrow_mut();
+		let backend = match backends.entry(device_type) {
+			Entry::Occupied(entry) => entry.into_mut(),
+			Entry::Vacant(entry) => entry.insert(volume_backend::get_preferred_backend(
+				device_type,
+				get_audio_backend(),
+			)?),
+		};
+		let result = change_device_volume(
+			backend.as_mut(),
+			change_type,
+			step,
+			context.device_name.as_deref(),
+			max_volume,
+		);
+		// The audio server might have restarted, reconnect on the next action
+		if let Err(error) = &result
+			&& volume_backend::needs_reconnect(error)
+		{
+			backends.remove(&device_type);
+		}
+		drop(backends);
+		let device = result?;
+		let kind = match device_type {
+			VolumeDeviceType::Sink => "sink-volume",
+			VolumeDeviceType::Source => "source-volume",
+		};
+		for window in self.osd_windows(kind, context.monitor_name.as_deref()) {
+			window.changed_volume(&device, device_type, max_volume);
+		}
+		let state = volume_state(&device, max_volume);
+		self.osd_shown(
+			context,
+			OsdEvent {
+				kind: kind.to_owned(),
+				value: state.volume,
+				max: max_volume as f64,
+				muted: state.muted,
+				label: state.description.clone(),
+				icon: volume_icon_name(
+					state.volume,
+					state.muted,
+					device_type == VolumeDeviceType::Source,
+				),
+				device: state.device.clone(),
+				..Default::default()
+			},
+		);
+		Ok(ActionReply::Volume(state))
+	}
+
+	fn brightness_action(
+		&self,
+		context: &ActionContext,
+		change_type: BrightnessChangeType,
+		value: Option<String>,
+	) -> Result<ActionReply, ActionError> {
+		let min_brightness = context
+			.min_brightness
+			.unwrap_or_else(get_default_min_brightness);
+		let mut brightness_backend = change_brightness(
+			change_type,
+			value,
+			context.device_name.clone(),
+			min_brightness,
+		)?;
+		for window in self.osd_windows("brightness", context.monitor_name.as_deref()) {
+			window.changed_brightness(brightness_backend.as_mut());
+		}
+		let state = brightness_state(brightness_backend.as_mut());
+		self.osd_shown(
+			context,
+			OsdEvent {
+				kind: "brightness".to_owned(),
+				value: state.value as f64,
+				max: state.max as f64,
+				icon: "display-brightness-symbolic".to_owned(),
+				device: state.device.clone(),
+				..Default::default()
+			},
+		);
+		Ok(ActionReply::Brightness(state))
+	}
+
+	fn keylock_shown(
+		&self,
+		context: &ActionContext,
+		key: KeysLocks,
+		kind: &str,
+		state: bool,
+	) -> ActionReply {
+		let (label, icon) = keylock_label_and_icon_name(&key, state);
+		self.osd_shown(
+			context,
+			OsdEvent {
+				kind: kind.to_owned(),
+				value: state as u8 as f64,
+				max: 1.0,
+				label,
+				icon: icon.to_owned(),
+				..Default::default()
+			},
+		);
+		ActionReply::KeyLock(KeyLockState {
+			key: kind.to_owned(),
+			state,
+		})
+	}
+
+	/// Notifies the renderers and the D-Bus listeners about the displayed OSD
+	fn osd_shown(&self, context: &ActionContext, event: OsdEvent) {
+		if is_osd_inhibited(&event.kind) {
+			return;
+		}
+		let event = OsdEvent {
+			monitor: context.monitor_name.clone().unwrap_or_default(),
+			..event
+		};
+		for window in self.choose_windows(context.monitor_name.as_deref()) {
+			window.osd_shown(&event);
+		}
+		if let Err(error) = self.osd_sender.try_send(event) {
+			eprintln!("Channel Send error: {}", error);
+		}
 	}
 }
@@ -0,0 +1,714 @@
+// The generated signal helpers take one argument per signal field
+#![allow(clippy::too_many_arguments)]
+
+use std::{collections::HashMap, future::pending, str::FromStr, sync::Mutex};
+
+use async_channel::{Receiver, Sender};
+use async_std::{stream::StreamExt, task};
+use zbus::{connection, fdo, interface, message::Header, object_server::SignalEmitter};
+
+use crate::actions::{request_actions, request_reload, ActionReply, ActionRequest, AppRequest};
+use crate::argtypes::{ActionContext, ArgTypes};
+use crate::config::{DBUS_INTERFACE_VERSION, DBUS_PATH, DBUS_SERVER_NAME};
+use crate::error::ActionError;
+use crate::global_utils::{progress_parser, segmented_progress_parser, step_parser};
+use crate::queries::{lock_key, query_brightness, query_lock_state, query_volume};
+use crate::state::{BrightnessState, InhibitState, OsdEvent, PlayerState, VolumeState};
+use crate::utils;
+
+pub struct DbusServer {
+	sender: Sender<ActionRequest>,
+	app_sender: Sender<AppRequest>,
+	/// Modifiers sent through `HandleAction`, kept per client until its next action
+	/// or until it disconnects
+	contexts: Mutex<HashMap<String, ActionContext>>,
+}
+
+/// Empty strings and negative numbers fall back to the server defaults
+#[interface(name = "org.erikreider.swayosd")]
+impl DbusServer {
+	#[zbus(property)]
+	fn version(&self) -> u32 {
+		DBUS_INTERFACE_VERSION
+	}
+
+	/// OSD margin from the top edge, from 0.0 up to, but not including, 1.0
+	#[zbus(property)]
+	fn top_margin(&self) -> f64 {
+		utils::get_top_margin() as f64
+	}
+
+	#[zbus(property)]
+	async fn set_top_margin(&self, margin: f64) -> zbus::Result<()> {
+		if !utils::TOP_MARGIN_RANGE.contains(&(margin as f32)) {
+			return Err(zbus::Error::from(fdo::Error::InvalidArgs(format!(
+				"{} is not a number between 0.0 and 1.0!",
+				margin
+			))));
+		}
+		utils::set_top_margin(margin as f32);
+		self.refresh_settings().await
+	}
+
+	#[zbus(property)]
+	fn show_percentage(&self) -> bool {
+		utils::get_show_percentage()
+	}
+
+	#[zbus(property)]
+	async fn set_show_percentage(&self, show: bool) -> zbus::Result<()> {
+		utils::set_show_percentage(show);
+		self.refresh_settings().await
+	}
+
+	/// Used by the volume actions without a max volume
+	#[zbus(property)]
+	fn max_volume(&self) -> u8 {
+		utils::get_default_max_volume()
+	}
+
+	#[zbus(property)]
+	async fn set_max_volume(&self, max_volume: u8) -> zbus::Result<()> {
+		utils::set_default_max_volume(max_volume);
+		self.refresh_settings().await
+	}
+
+	/// Used by the brightness actions without a min brightness
+	#[zbus(property)]
+	fn min_brightness(&self) -> u32 {
+		utils::get_default_min_brightness()
+	}
+
+	#[zbus(property)]
+	async fn set_min_brightness(&self, min_brightness: u32) -> zbus::Result<()> {
+		if min_brightness > 100 {
+			return Err(zbus::Error::from(fdo::Error::InvalidArgs(format!(
+				"{} is not a number between 0 and 100!",
+				min_brightness
+			))));
+		}
+		utils::set_default_min_brightness(min_brightness);
+		self.refresh_settings().await
+	}
+
+	/// While inhibited, the actions are applied without displaying the OSDs
+	#[zbus(property)]
+	fn inhibited(&self) -> bool {
+		utils::get_inhibited()
+	}
+
+	#[zbus(property)]
+	async fn set_inhibited(&self, inhibited: bool) -> zbus::Result<()> {
+		// Not sent as an Inhibit action, which would announce the change a second time
+		utils::set_inhibited(inhibited);
+		self.send_app_request(AppRequest::RefreshInhibit).await
+	}
+
+	/// The kinds of OSDs that are still displayed while inhibited, same as in `OsdShown`
+	#[zbus(property)]
+	fn inhibit_shown_kinds(&self) -> Vec<String> {
+		utils::get_inhibit_shown_kinds()
+	}
+
+	#[zbus(property)]
+	async fn set_inhibit_shown_kinds(&self, kinds: Vec<String>) -> zbus::Result<()> {
+		utils::set_inhibit_shown_kinds(kinds);
+		self.send_app_request(AppRequest::RefreshInhibit).await
+	}
+
+	/// Emitted every time an OSD is displayed. The device was added after the
+	/// other arguments, so older listeners can still read the signal
+	#[zbus(signal)]
+	async fn osd_shown(
+		emitter: &SignalEmitter<'_>,
+		kind: &str,
+		value: f64,
+		max: f64,
+		muted: bool,
+		label: &str,
+		icon: &str,
+		monitor: &str,
+		device: &str,
+	) -> zbus::Result<()>;
+
+	/// Mode is one of raise|lower|mute-toggle
+	async fn set_sink_volume(
+		&self,
+		device: &str,
+		mode: &str,
+		step: i32,
+		max: i32,
+		monitor: &str,
+	) -> Result<VolumeState, ActionError> {
+		self.change_volume(false, device, mode, step, max, monitor)
+			.await
+	}
+
+	/// Mode is one of raise|lower|mute-toggle
+	async fn set_source_volume(
+		&self,
+		device: &str,
+		mode: &str,
+		step: i32,
+		max: i32,
+		monitor: &str,
+	) -> Result<VolumeState, ActionError> {
+		self.change_volume(true, device, mode, step, max, monitor)
+			.await
+	}
+
+	/// Mode is one of raise|lower|set
+	async fn set_brightness(
+		&self,
+		device: &str,
+		mode: &str,
+		value: i32,
+		min: i32,
+		monitor: &str,
+	) -> Result<BrightnessState, ActionError> {
+		let arg_type = match mode {
+			"raise" => ArgTypes::BrightnessRaise,
+			"lower" => ArgTypes::BrightnessLower,
+			"set" if value >= 0 => ArgTypes::BrightnessSet,
+			"set" => return Err(ActionError::InvalidValue("Missing brightness value".into())),
+			mode => return Err(unknown_mode(mode)),
+		};
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::DeviceName, device);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		if min >= 0 {
+			actions.push((ArgTypes::MinBrightness, Some(min.to_string())));
+		}
+		actions.push((arg_type, (value >= 0).then(|| value.to_string())));
+
+		match self.request(actions).await? {
+			ActionReply::Brightness(state) => Ok(state),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Key is one of caps-lock|num-lock|scroll-lock.
+	/// A state of 0 or 1 is displayed as is, otherwise the state is read from the LED
+	async fn show_key_lock(
+		&self,
+		key: &str,
+		state: i32,
+		led: &str,
+		monitor: &str,
+	) -> Result<bool, ActionError> {
+		let (arg_type, _) = lock_key(key)?;
+		let data = match state {
+			0 | 1 => Some(state.to_string()),
+			_ => (!led.is_empty()).then(|| led.to_owned()),
+		};
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((arg_type, data));
+
+		match self.request(actions).await? {
+			ActionReply::KeyLock(state) => Ok(state.state),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Action is one of play-pause|play|pause|stop|next|prev|shuffle
+	async fn control_player(
+		&self,
+		action: &str,
+		player: &str,
+		monitor: &str,
+	) -> Result<PlayerState, ActionError> {
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::Player, player);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((ArgTypes::Playerctl, Some(action.to_owned())));
+
+		match self.request(actions).await? {
+			ActionReply::Player(state) => Ok(state),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	async fn show_custom_message(
+		&self,
+		message: &str,
+		icon: &str,
+		monitor: &str,
+	) -> Result<(), ActionError> {
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::CustomIcon, icon);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((ArgTypes::CustomMessage, Some(message.to_owned())));
+
+		self.request(actions).await?;
+		Ok(())
+	}
+
+	/// Fraction is clamped between 0.0 and 1.0
+	async fn show_custom_progress(
+		&self,
+		fraction: f64,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+	) -> Result<f64, ActionError> {
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::CustomProgressText, text);
+		push_modifier(&mut actions, ArgTypes::CustomIcon, icon);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((ArgTypes::CustomProgress, Some(fraction.to_string())));
+
+		match self.request(actions).await? {
+			ActionReply::Progress(fraction) => Ok(fraction),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Value is clamped to the number of segments
+	async fn show_custom_segmented_progress(
+		&self,
+		value: u32,
+		n_segments: u32,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+	) -> Result<(u32, u32), ActionError> {
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::CustomProgressText, text);
+		push_modifier(&mut actions, ArgTypes::CustomIcon, icon);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((
+			ArgTypes::CustomSegmentedProgress,
+			Some(format!("{}:{}", value, n_segments)),
+		));
+
+		match self.request(actions).await? {
+			ActionReply::SegmentedProgress(value, n_segments) => Ok((value, n_segments)),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Displays the progress under the ID, replacing the previous progress with the same ID.
+	/// Empty text, icon and monitor keep the values of the previous progress.
+	/// A persistent progress stays displayed until `CloseProgress` is called
+	async fn show_tracked_progress(
+		&self,
+		id: &str,
+		fraction: f64,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+		persistent: bool,
+	) -> Result<f64, ActionError> {
+		let mut actions = job_modifiers(id, persistent)?;
+		push_modifier(&mut actions, ArgTypes::CustomProgressText, text);
+		push_modifier(&mut actions, ArgTypes::CustomIcon, icon);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((ArgTypes::CustomProgress, Some(fraction.to_string())));
+
+		match self.request(actions).await? {
+			ActionReply::Progress(fraction) => Ok(fraction),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Same as `ShowTrackedProgress` with a segmented progress
+	async fn show_tracked_segmented_progress(
+		&self,
+		id: &str,
+		value: u32,
+		n_segments: u32,
+		text: &str,
+		icon: &str,
+		monitor: &str,
+		persistent: bool,
+	) -> Result<(u32, u32), ActionError> {
+		let mut actions = job_modifiers(id, persistent)?;
+		push_modifier(&mut actions, ArgTypes::CustomProgressText, text);
+		push_modifier(&mut actions, ArgTypes::CustomIcon, icon);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		actions.push((
+			ArgTypes::CustomSegmentedProgress,
+			Some(format!("{}:{}", value, n_segments)),
+		));
+
+		match self.request(actions).await? {
+			ActionReply::SegmentedProgress(value, n_segments) => Ok((value, n_segments)),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Mode is one of on|off|toggle
+	async fn inhibit(&self, mode: &str) -> Result<InhibitState, ActionError> {
+		let actions = vec![(ArgTypes::Inhibit, Some(mode.to_owned()))];
+		match self.request(actions).await? {
+			ActionReply::Inhibit(state) => Ok(state),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Hides the tracked progress and forgets it. Unknown IDs are ignored
+	async fn close_progress(&self, id: &str) -> Result<(), ActionError> {
+		self.request(vec![(ArgTypes::CloseProgress, Some(id.to_owned()))])
+			.await?;
+		Ok(())
+	}
+
+	/// Device kind is one of sink|source. Doesn't display the OSD
+	async fn get_volume(
+		&self,
+		device_kind: &str,
+		device: &str,
+	) -> Result<VolumeState, ActionError> {
+		let device = (!device.is_empty()).then(|| device.to_owned());
+		query_volume(device_kind, device).await
+	}
+
+	/// Doesn't display the OSD
+	async fn get_brightness(&self, device: &str) -> Result<BrightnessState, ActionError> {
+		let device = (!device.is_empty()).then(|| device.to_owned());
+		query_brightness(device).await
+	}
+
+	/// Rereads the config file and the user stylesheet
+	async fn reload_config(&self) -> Result<(), ActionError> {
+		request_reload(&self.app_sender).await
+	}
+
+	/// Key is one of caps-lock|num-lock|scroll-lock. Doesn't display the OSD
+	async fn get_lock_state(&self, key: &str) -> Result<bool, ActionError> {
+		query_lock_state(key).await
+	}
+
+	/// Compatibility shim for clients that send one action at a time
+	pub async fn handle_action(
+		&self,
+		arg_type: String,
+		data: String,
+		#[zbus(header)] header: Header<'_>,
+	) -> Result<bool, ActionError> {
+		let arg_type = ArgTypes::from_str(&arg_type)
+			.map_err(|_| ActionError::InvalidValue(format!("Unknown action: \"{}\"", arg_type)))?;
+		let data = (!data.is_empty()).then_some(data);
+
+		// Keep the modifiers of each client separate until its next action
+		let client = header
+			.sender()
+			.map(|name| name.to_string())
+			.unwrap_or_default();
+		let context = {
+			let mut contexts = self.contexts.lock().unwrap();
+			if contexts
+				.entry(client.clone())
+				.or_default()
+				.set_modifier(&arg_type, &data)
+			{
+				return Ok(true);
+			}
+			contexts.remove(&client).unwrap_or_default()
+		};
+		let device = context.device_name.as_deref().unwrap_or_default();
+		let monitor = context.monitor_name.as_deref().unwrap_or_default();
+		let icon = context.icon_name.as_deref().unwrap_or_default();
+		let text = context.progress_text.as_deref().unwrap_or_default();
+		let player = context.player.as_deref().unwrap_or_default();
+		let max = context.max_volume.map_or(-1, i32::from);
+		let min = context.min_brightness.map_or(-1, |min| min as i32);
+
+		let data = data.unwrap_or_default();
+		let step = || step_parser(&data).map_err(ActionError::InvalidValue);
+		match arg_type {
+			ArgTypes::SinkVolumeRaise => self
+				.change_volume(false, device, "raise", step()?, max, monitor)
+				.await
+				.map(drop),
+			ArgTypes::SinkVolumeLower => self
+				.change_volume(false, device, "lower", step()?, max, monitor)
+				.await
+				.map(drop),
+			ArgTypes::SinkVolumeMuteToggle => self
+				.change_volume(false, device, "mute-toggle", -1, max, monitor)
+				.await
+				.map(drop),
+			ArgTypes::SourceVolumeRaise => self
+				.change_volume(true, device, "raise", step()?, max, monitor)
+				.await
+				.map(drop),
+			ArgTypes::SourceVolumeLower => self
+				.change_volume(true, device, "lower", step()?, max, monitor)
+				.await
+				.map(drop),
+			ArgTypes::SourceVolumeMuteToggle => self
+				.change_volume(true, device, "mute-toggle", -1, max, monitor)
+				.await
+				.map(drop),
+			ArgTypes::BrightnessRaise => self
+				.set_brightness(device, "raise", step()?, min, monitor)
+				.await
+				.map(drop),
+			ArgTypes::BrightnessLower => self
+				.set_brightness(device, "lower", step()?, min, monitor)
+				.await
+				.map(drop),
+			ArgTypes::BrightnessSet => self
+				.set_brightness(device, "set", step()?, min, monitor)
+				.await
+				.map(drop),
+			ArgTypes::CapsLock | ArgTypes::NumLock | ArgTypes::ScrollLock => {
+				let key = match arg_type {
+					ArgTypes::CapsLock => "caps-lock",
+					ArgTypes::NumLock => "num-lock",
+					_ => "scroll-lock",
+				};
+				// The data is either the lock state or the LED name
+				let (state, led) = match data.parse::<i32>() {
+					Ok(state) => (state, ""),
+					Err(_) => (-1, data.as_str()),
+				};
+				self.show_key_lock(key, state, led, monitor).await.map(drop)
+			}
+			ArgTypes::Playerctl => self.control_player(&data, player, monitor).await.map(drop),
+			ArgTypes::CustomMessage => self.show_custom_message(&data, icon, monitor).await,
+			ArgTypes::CustomProgress => {
+				let fraction = progress_parser(&data).map_err(ActionError::InvalidValue)?;
+				match context.progress_id.as_deref() {
+					Some(id) => self
+						.show_tracked_progress(
+							id,
+							fraction,
+							text,
+							icon,
+							monitor,
+							context.persistent,
+						)
+						.await
+						.map(drop),
+					None => self
+						.show_custom_progress(fraction, text, icon, monitor)
+						.await
+						.map(drop),
+				}
+			}
+			ArgTypes::CustomSegmentedProgress => match segmented_progress_parser(&data) {
+				Ok((value, n_segments)) => match context.progress_id.as_deref() {
+					Some(id) => self
+						.show_tracked_segmented_progress(
+							id,
+							value,
+							n_segments,
+							text,
+							icon,
+							monitor,
+							context.persistent,
+						)
+						.await
+						.map(drop),
+					None => self
+						.show_custom_segmented_progress(value, n_segments, text, icon, monitor)
+						.await
+						.map(drop),
+				},
+				Err(error) => Err(ActionError::InvalidValue(error)),
+			},
+			// Internal actions are passed through as is
+			arg_type => {
+				let mut actions = context.to_modifiers();
+				actions.push((arg_type, (!data.is_empty()).then_some(data)));
+				self.request(actions).await.map(drop)
+			}
+		}
+		.map(|_| true)
+	}
+}
+
+impl DbusServer {
+	pub async fn init(
+		sender: Sender<ActionRequest>,
+		app_sender: Sender<AppRequest>,
+		osd_receiver: Receiver<OsdEvent>,
+		settings_receiver: Receiver<()>,
+		ready_sender: Sender<()>,
+	) -> zbus::Result<()> {
+		let connection = connection::Builder::session()?
+			.name(DBUS_SERVER_NAME)?
+			.serve_at(
+				DBUS_PATH,
+				DbusServer {
+					sender,
+					app_sender,
+					contexts: Mutex::new(HashMap::new()),
+				},
+			)?
+			.build()
+			.await?;
+		let iface_ref = connection
+			.object_server()
+			.interface::<_, DbusServer>(DBUS_PATH)
+			.await?;
+		// The name is acquired, the actions sent from now on are queued until the windows exist
+		if let Err(error) = ready_sender.send(()).await {
+			eprintln!("Channel Send error: {}", error);
+		}
+
+		// Forget the modifiers of the clients that disconnected before sending an action
+		task::spawn({
+			let connection = connection.clone();
+			let iface_ref = iface_ref.clone();
+			async move {
+				let result = async {
+					let proxy = fdo::DBusProxy::new(&connection).await?;
+					let mut changes = proxy.receive_name_owner_changed().await?;
+					while let Some(change) = changes.next().await {
+						let args = change.args()?;
+						if args.new_owner().is_none() {
+							let server = iface_ref.get().await;
+							server.contexts.lock().unwrap().remove(args.name().as_str());
+						}
+					}
+					zbus::Result::Ok(())
+				}
+				.await;
+				if let Err(error) = result {
+					eprintln!("NameOwnerChanged Error: {}", error)
+				}
+			}
+		});
+
+		// Notify about the settings that changed when the config was reloaded
+		// or the inhibit state changed
+		task::spawn({
+			let iface_ref = iface_ref.clone();
+			async move {
+				while let Ok(()) = settings_receiver.recv().await {
+					let server = iface_ref.get().await;
+					let emitter = iface_ref.signal_emitter();
+					let result = async {
+						server.top_margin_changed(emitter).await?;
+						server.show_percentage_changed(emitter).await?;
+						server.max_volume_changed(emitter).await?;
+						server.min_brightness_changed(emitter).await?;
+						server.inhibited_changed(emitter).await?;
+						server.inhibit_shown_kinds_changed(emitter).await
+					}
+					.await;
+					if let Err(error) = result {
+						eprintln!("Signal Error: {}", error)
+					}
+				}
+			}
+		});
+
+		// Forward every displayed OSD as a signal
+		while let Ok(event) = osd_receiver.recv().await {
+			let signal_result = DbusServer::osd_shown(
+				iface_ref.signal_emitter(),
+				&event.kind,
+				event.value,
+				event.max,
+				event.muted,
+				&event.label,
+				&event.icon,
+				&event.monitor,
+				&event.device,
+			)
+			.await;
+			if let Err(error) = signal_result {
+				eprintln!("Signal Error: {}", error)
+			}
+		}
+		pending::<()>().await;
+		Ok(())
+	}
+
+	async fn change_volume(
+		&self,
+		is_source: bool,
+		device: &str,
+		mode: &str,
+		step: i32,
+		max: i32,
+		monitor: &str,
+	) -> Result<VolumeState, ActionError> {
+		let arg_type = match (is_source, mode) {
+			(false, "raise") => ArgTypes::SinkVolumeRaise,
+			(false, "lower") => ArgTypes::SinkVolumeLower,
+			(false, "mute-toggle") => ArgTypes::SinkVolumeMuteToggle,
+			(true, "raise") => ArgTypes::SourceVolumeRaise,
+			(true, "lower") => ArgTypes::SourceVolumeLower,
+			(true, "mute-toggle") => ArgTypes::SourceVolumeMuteToggle,
+			(_, mode) => return Err(unknown_mode(mode)),
+		};
+		if max > u8::MAX as i32 {
+			return Err(ActionError::InvalidValue(format!(
+				"{} is not a number between 0 and {}!",
+				max,
+				u8::MAX
+			)));
+		}
+		let mut actions = Vec::new();
+		push_modifier(&mut actions, ArgTypes::DeviceName, device);
+		push_modifier(&mut actions, ArgTypes::MonitorName, monitor);
+		if max >= 0 {
+			actions.push((ArgTypes::MaxVolume, Some(max.to_string())));
+		}
+		actions.push((arg_type, (step >= 0).then(|| step.to_string())));
+
+		match self.request(actions).await? {
+			ActionReply::Volume(state) => Ok(state),
+			reply => Err(unexpected_reply(reply)),
+		}
+	}
+
+	/// Sends the actions to the GTK Application and waits for the resulting state
+	async fn request(
+		&self,
+		actions: Vec<(ArgTypes, Option<String>)>,
+	) -> Result<ActionReply, ActionError> {
+		request_actions(&self.sender, actions).await
+	}
+
+	/// Applies the changed settings to the existing windows
+	async fn refresh_settings(&self) -> zbus::Result<()> {
+		self.send_app_request(AppRequest::RefreshSettings).await
+	}
+
+	async fn send_app_request(&self, request: AppRequest) -> zbus::Result<()> {
+		self.app_sender
+			.send(request)
+			.await
+			.map_err(|error| zbus::Error::Failure(format!("Channel Send error: {}", error)))
+	}
+}
+
+fn push_modifier(actions: &mut Vec<(ArgTypes, Option<String>)>, arg_type: ArgTypes, value: &str) {
+	if !value.is_empty() {
+		actions.push((arg_type, Some(value.to_owned())));
+	}
+}
+
+fn job_modifiers(
+	id: &str,
+	persistent: bool,
+) -> Result<Vec<(ArgTypes, Option<String>)>, ActionError> {
+	if id.is_empty() {
+		return Err(ActionError::InvalidValue(
+			"The progress ID can't be empty".to_owned(),
+		));
+	}
+	let mut actions = vec![(ArgTypes::ProgressId, Some(id.to_owned()))];
+	if persistent {
+		actions.push((ArgTypes::Persistent, None));
+	}
+	Ok(actions)
+}
+
+fn unknown_mode(mode: &str) -> ActionError {
+	ActionError::InvalidValue(format!("Unknown mode: \"{}\"", mode))
+}
+
+fn unexpected_reply(reply: ActionReply) -> ActionError {
+	ActionError::Failed(format!(
+		"Action didn't produce the expected state: {:?}",
+		reply
+	))
+}
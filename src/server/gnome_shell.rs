@@ -0,0 +1,104 @@
+use std::{collections::HashMap, future::pending};
+
+use async_channel::Sender;
+use zbus::{
+	connection, fdo, interface,
+	zvariant::{OwnedValue, Value},
+};
+
+use crate::actions::{send_actions, ActionRequest};
+use crate::argtypes::ArgTypes;
+use crate::utils::value_to_f64;
+
+const GNOME_SHELL_NAME: &str = "org.gnome.Shell";
+const GNOME_SHELL_PATH: &str = "/org/gnome/Shell";
+
+/// The OSD part of the GNOME Shell interface
+pub struct GnomeShell {
+	sender: Sender<ActionRequest>,
+}
+
+#[interface(name = "org.gnome.Shell")]
+impl GnomeShell {
+	/// Params are icon, label, level, max_level and connector, all optional
+	#[zbus(name = "ShowOSD")]
+	async fn show_osd(&self, params: HashMap<String, OwnedValue>) -> fdo::Result<()> {
+		let string_param = |key: &str| match params.get(key).map(|value| &**value) {
+			Some(Value::Str(value)) if !value.is_empty() => Some(value.to_string()),
+			_ => None,
+		};
+		let number_param = |key: &str| params.get(key).and_then(|value| value_to_f64(value));
+
+		let mut actions = Vec::new();
+		if let Some(connector) = string_param("connector") {
+			actions.push((ArgTypes::MonitorName, Some(connector)));
+		}
+		if let Some(icon) = string_param("icon") {
+			actions.push((ArgTypes::CustomIcon, Some(themed_icon_name(&icon))));
+		}
+		let label = string_param("label");
+		match number_param("level") {
+			Some(level) => {
+				if let Some(label) = label {
+					actions.push((ArgTypes::CustomProgressText, Some(label)));
+				}
+				let max_level = number_param("max_level")
+					.filter(|max| *max > 0.0)
+					.unwrap_or(1.0);
+				let fraction = level / max_level;
+				actions.push((ArgTypes::CustomProgress, Some(fraction.to_string())));
+			}
+			None => actions.push((ArgTypes::CustomMessage, Some(label.unwrap_or_default()))),
+		}
+
+		send_actions(&self.sender, actions).await
+	}
+}
+
+impl GnomeShell {
+	pub async fn init(sender: Sender<ActionRequest>) -> zbus::Result<()> {
+		let _connection = connection::Builder::session()?
+			.name(GNOME_SHELL_NAME)?
+			.serve_at(GNOME_SHELL_PATH, GnomeShell { sender })?
+			.build()
+			.await?;
+		pending::<()>().await;
+		Ok(())
+	}
+}
+
+/// The icon is a serialized GIcon, which is either a plain icon name
+/// or ". GThemedIcon name fallback-name..."
+fn themed_icon_name(icon: &str) -> String {
+	match icon.strip_prefix(". GThemedIcon ") {
+		Some(names) => names
+			.split_whitespace()
+			.next()
+			.unwrap_or_default()
+			.to_owned(),
+		None => icon.to_owned(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn themed_icon_name_keeps_plain_names() {
+		assert_eq!(
+			themed_icon_name("audio-volume-high-symbolic"),
+			"audio-volume-high-symbolic"
+		);
+		assert_eq!(themed_icon_name(""), "");
+	}
+
+	#[test]
+	fn themed_icon_name_uses_the_first_themed_name() {
+		assert_eq!(
+			themed_icon_name(". GThemedIcon display-brightness-symbolic display-brightness"),
+			"display-brightness-symbolic"
+		);
+		assert_eq!(themed_icon_name(". GThemedIcon "), "");
+	}
+}
@@ -0,0 +1,145 @@
+use std::future::pending;
+
+use async_channel::Sender;
+use zbus::{connection, fdo, interface};
+
+use crate::actions::{send_actions, ActionRequest};
+use crate::argtypes::ArgTypes;
+use crate::osd_window::volume_icon_name;
+
+const PLASMASHELL_NAME: &str = "org.kde.plasmashell";
+const OSD_SERVICE_PATH: &str = "/org/kde/osdService";
+
+/// The OSD service of Plasma, used by KDE apps and plasma-pa style scripts
+pub struct KdeOsdService {
+	sender: Sender<ActionRequest>,
+}
+
+#[interface(name = "org.kde.osdService")]
+impl KdeOsdService {
+	#[zbus(name = "volumeChanged")]
+	async fn volume_changed(&self, percent: i32, maximum_percent: i32) -> fdo::Result<()> {
+		let icon = volume_icon_name(percent as f64, false, false);
+		self.progress(percent, maximum_percent, &icon, None).await
+	}
+
+	#[zbus(name = "microphoneVolumeChanged")]
+	async fn microphone_volume_changed(&self, percent: i32) -> fdo::Result<()> {
+		let icon = volume_icon_name(percent as f64, false, true);
+		self.progress(percent, 100, &icon, None).await
+	}
+
+	#[zbus(name = "mediaPlayerVolumeChanged")]
+	async fn media_player_volume_changed(
+		&self,
+		percent: i32,
+		player_name: String,
+		player_icon_name: String,
+	) -> fdo::Result<()> {
+		self.progress(percent, 100, &player_icon_name, Some(player_name))
+			.await
+	}
+
+	#[zbus(name = "brightnessChanged")]
+	async fn brightness_changed(&self, percent: i32) -> fdo::Result<()> {
+		self.progress(percent, 100, "display-brightness-symbolic", None)
+			.await
+	}
+
+	#[zbus(name = "keyboardBrightnessChanged")]
+	async fn keyboard_brightness_changed(&self, percent: i32) -> fdo::Result<()> {
+		let value = format!("{}:100", percent.clamp(0, 100));
+		send_actions(&self.sender, vec![(ArgTypes::KbdBacklight, Some(value))]).await
+	}
+
+	#[zbus(name = "kbdLayoutChanged")]
+	async fn kbd_layout_changed(&self, layout_name: String) -> fdo::Result<()> {
+		self.message(layout_name, "input-keyboard-symbolic").await
+	}
+
+	#[zbus(name = "virtualDesktopChanged")]
+	async fn virtual_desktop_changed(
+		&self,
+		current_virtual_desktop_name: String,
+	) -> fdo::Result<()> {
+		self.message(current_virtual_desktop_name, "user-desktop-symbolic")
+			.await
+	}
+
+	#[zbus(name = "touchpadEnabledChanged")]
+	async fn touchpad_enabled_changed(&self, touchpad_enabled: bool) -> fdo::Result<()> {
+		self.toggled("Touchpad", touchpad_enabled, "input-touchpad-symbolic")
+			.await
+	}
+
+	#[zbus(name = "wifiEnabledChanged")]
+	async fn wifi_enabled_changed(&self, wifi_enabled: bool) -> fdo::Result<()> {
+		let icon = match wifi_enabled {
+			true => "network-wireless-symbolic",
+			false => "network-wireless-disabled-symbolic",
+		};
+		self.toggled("Wi-Fi", wifi_enabled, icon).await
+	}
+
+	#[zbus(name = "bluetoothEnabledChanged")]
+	async fn bluetooth_enabled_changed(&self, bluetooth_enabled: bool) -> fdo::Result<()> {
+		let icon = match bluetooth_enabled {
+			true => "bluetooth-active-symbolic",
+			false => "bluetooth-disabled-symbolic",
+		};
+		self.toggled("Bluetooth", bluetooth_enabled, icon).await
+	}
+
+	#[zbus(name = "showText")]
+	async fn show_text(&self, icon: String, text: String) -> fdo::Result<()> {
+		self.message(text, &icon).await
+	}
+}
+
+impl KdeOsdService {
+	pub async fn init(sender: Sender<ActionRequest>) -> zbus::Result<()> {
+		let _connection = connection::Builder::session()?
+			.name(PLASMASHELL_NAME)?
+			.serve_at(OSD_SERVICE_PATH, KdeOsdService { sender })?
+			.build()
+			.await?;
+		pending::<()>().await;
+		Ok(())
+	}
+
+	async fn progress(
+		&self,
+		percent: i32,
+		maximum_percent: i32,
+		icon: &str,
+		text: Option<String>,
+	) -> fdo::Result<()> {
+		let fraction = percent as f64 / maximum_percent.max(1) as f64;
+		let mut actions = Vec::new();
+		if !icon.is_empty() {
+			actions.push((ArgTypes::CustomIcon, Some(icon.to_owned())));
+		}
+		if let Some(text) = text.filter(|text| !text.is_empty()) {
+			actions.push((ArgTypes::CustomProgressText, Some(text)));
+		}
+		actions.push((ArgTypes::CustomProgress, Some(fraction.to_string())));
+		send_actions(&self.sender, actions).await
+	}
+
+	async fn message(&self, text: String, icon: &str) -> fdo::Result<()> {
+		let mut actions = Vec::new();
+		if !icon.is_empty() {
+			actions.push((ArgTypes::CustomIcon, Some(icon.to_owned())));
+		}
+		actions.push((ArgTypes::CustomMessage, Some(text)));
+		send_actions(&self.sender, actions).await
+	}
+
+	async fn toggled(&self, name: &str, enabled: bool, icon: &str) -> fdo::Result<()> {
+		let state = match enabled {
+			true => "On",
+			false => "Off",
+		};
+		self.message(format!("{} {}", name, state), icon).await
+	}
+}
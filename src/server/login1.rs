@@ -1,4 +1,6 @@
-use zbus::{proxy, Connection};
+#![allow(dead_code)]
+
+use zbus::{proxy, zvariant::OwnedObjectPath, Connection};
 
 #[proxy(
 	default_service = "org.freedesktop.login1",
@@ -8,6 +10,35 @@ use zbus::{proxy, Connection};
 pub trait Login1 {
 	#[zbus(signal, name = "PrepareForSleep")]
 	async fn prepare_for_sleep(&self, value: bool) -> zbus::Result<()>;
+
+	fn list_seats(&self) -> zbus::Result<Vec<(String, OwnedObjectPath)>>;
+
+	fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+
+	#[zbus(name = "GetSessionByPID")]
+	fn get_session_by_pid(&self, pid: u32) -> zbus::Result<OwnedObjectPath>;
+
+	fn get_user(&self, uid: u32) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+	default_service = "org.freedesktop.login1",
+	interface = "org.freedesktop.login1.Session"
+)]
+pub trait Session {
+	/// The seat ID and object path, empty for sessions without a seat
+	#[zbus(property)]
+	fn seat(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+}
+
+#[proxy(
+	default_service = "org.freedesktop.login1",
+	interface = "org.freedesktop.login1.User"
+)]
+pub trait User {
+	/// The session ID and object path of the graphical session of the user
+	#[zbus(property)]
+	fn display(&self) -> zbus::Result<(String, OwnedObjectPath)>;
 }
 
 pub struct Login1 {}
@@ -19,4 +50,31 @@ impl Login1 {
 
 		Ok(proxy)
 	}
+
+	/// The seat of the session this process belongs to, found through $XDG_SESSION_ID,
+	/// the process or the graphical session of the user (for systemd user services).
+	/// None if the session has no seat
+	pub async fn own_seat() -> zbus::Result<Option<String>> {
+		let proxy = Self::init().await?;
+		let session_path = match std::env::var("XDG_SESSION_ID") {
+			Ok(id) if !id.is_empty() => proxy.get_session(&id).await?,
+			_ => match proxy.get_session_by_pid(std::process::id()).await {
+				Ok(path) => path,
+				Err(_) => {
+					let user_path = proxy.get_user(nix::unistd::getuid().as_raw()).await?;
+					let user = UserProxy::builder(proxy.inner().connection())
+						.path(user_path)?
+						.build()
+						.await?;
+					user.display().await?.1
+				}
+			},
+		};
+		let session = SessionProxy::builder(proxy.inner().connection())
+			.path(session_path)?
+			.build()
+			.await?;
+		let (seat, _) = session.seat().await?;
+		Ok((!seat.is_empty()).then_some(seat))
+	}
 }
@@ -1,9 +1,19 @@
+mod actions;
 mod application;
+mod dbus_server;
+mod gnome_shell;
+mod kde_osd;
 mod login1;
+mod notifications;
 mod osd_window;
+mod queries;
+mod renderer;
+mod socket;
+mod systemd;
 mod upower;
 mod utils;
 mod widgets;
+mod wob;
 
 #[path = "../args.rs"]
 mod args;
@@ -11,8 +21,14 @@ mod args;
 mod argtypes;
 #[path = "../config.rs"]
 mod config;
+#[path = "../error.rs"]
+mod error;
 #[path = "../global_utils.rs"]
 mod global_utils;
+#[path = "../ipc.rs"]
+mod ipc;
+#[path = "../state.rs"]
+mod state;
 
 #[path = "../brightness_backend/mod.rs"]
 mod brightness_backend;
@@ -20,64 +36,152 @@ mod brightness_backend;
 #[path = "../mpris-backend/mod.rs"]
 mod playerctl;
 
+#[path = "../volume_backend/mod.rs"]
+mod volume_backend;
+
 #[macro_use]
 extern crate shrinkwraprs;
 
 #[macro_use]
 extern crate cascade;
 
+use actions::{ActionRequest, AppRequest};
 use application::SwayOSDApplication;
-use argtypes::ArgTypes;
-use async_channel::Sender;
+use args::Renderer;
 use clap::Parser;
-use config::{DBUS_PATH, DBUS_SERVER_NAME};
+use dbus_server::DbusServer;
+use gnome_shell::GnomeShell;
 use gtk::{
 	gdk::Display,
 	gio::{self, Resource},
 	glib::Bytes,
 	CssProvider, IconTheme,
 };
-use std::{future::pending, str::FromStr, sync::Arc};
-use utils::{get_system_css_path, user_style_path};
-use zbus::{connection, interface};
+use kde_osd::KdeOsdService;
+use notifications::NotificationsBridge;
+use socket::SocketServer;
+use state::OsdEvent;
+use std::sync::Arc;
+use utils::get_system_css_path;
+use wob::WobFifo;
 
-struct DbusServer {
-	sender: Sender<(ArgTypes, String)>,
-}
+const GRESOURCE_BASE_PATH: &str = "/org/erikreider/swayosd";
 
-#[interface(name = "org.erikreider.swayosd")]
-impl DbusServer {
-	pub async fn handle_action(&self, arg_type: String, data: String) -> bool {
-		let arg_type = match ArgTypes::from_str(&arg_type) {
-			Ok(arg_type) => arg_type,
-			Err(other_type) => {
-				eprintln!("Unknown action in Dbus handle_action: {:?}", other_type);
-				return false;
-			}
-		};
-		if let Err(error) = self.sender.send((arg_type, data)).await {
-			eprintln!("Channel Send error: {}", error);
-			return false;
-		}
-		true
+fn main() {
+	let args = Arc::new(args::ArgsServer::parse());
+
+	// The headless renderers don't need a display
+	if args.renderer == Renderer::Gtk {
+		init_gtk();
 	}
-}
 
-impl DbusServer {
-	async fn init(sender: Sender<(ArgTypes, String)>) -> zbus::Result<()> {
-		let _connection = connection::Builder::session()?
-			.name(DBUS_SERVER_NAME)?
-			.serve_at(DBUS_PATH, DbusServer { sender })?
-			.build()
-			.await?;
-		pending::<()>().await;
-		Ok(())
+	// Parse Config
+	let server_config = Arc::new(
+		config::user::read_user_config(args.config.as_deref())
+			.expect("Failed to parse config file")
+			.server,
+	);
+
+	// Leaves room for a burst of key repeats to be coalesced by the application
+	let (sender, receiver) = async_channel::bounded::<ActionRequest>(32);
+	let (osd_sender, osd_receiver) = async_channel::unbounded::<OsdEvent>();
+	let (app_sender, app_receiver) = async_channel::bounded::<AppRequest>(1);
+	let (settings_sender, settings_receiver) = async_channel::unbounded::<()>();
+	// Sent once the D-Bus name is acquired and once the windows exist
+	let (ready_sender, ready_receiver) = async_channel::unbounded::<()>();
+	// Read the wob compatible FIFO
+	if let Some(path) = args.wob_fifo.clone().or(server_config.wob_fifo.clone()) {
+		let styles = server_config.wob_styles.clone().unwrap_or_default();
+		match WobFifo::new(path, styles, sender.clone()) {
+			Ok(wob_fifo) => wob_fifo.start(),
+			Err(error) => eprintln!("Could not create the wob FIFO: {}", error),
+		}
+	}
+	// Display the notifications with OSD hints
+	if server_config.notifications.unwrap_or(false) {
+		let sender = sender.clone();
+		async_std::task::spawn(async move {
+			if let Err(error) = NotificationsBridge::init(sender).await {
+				eprintln!("Could not start the notifications bridge: {}", error);
+			}
+		});
+	}
+	// Display the OSDs of tools made for GNOME Shell
+	if server_config.gnome_shell_osd.unwrap_or(false) {
+		let sender = sender.clone();
+		async_std::task::spawn(async move {
+			if let Err(error) = GnomeShell::init(sender).await {
+				eprintln!("Could not export the GNOME Shell OSD interface: {}", error);
+			}
+		});
+	}
+	// Display the OSDs of tools made for KDE Plasma
+	if server_config.kde_osd_service.unwrap_or(false) {
+		let sender = sender.clone();
+		async_std::task::spawn(async move {
+			if let Err(error) = KdeOsdService::init(sender).await {
+				eprintln!("Could not export the KDE osdService interface: {}", error);
+			}
+		});
+	}
+	// Listen on the unix socket, used by clients without a session bus
+	{
+		let sender = sender.clone();
+		async_std::task::spawn(async move {
+			if let Err(error) = SocketServer::init(sender).await {
+				eprintln!("Could not listen on the unix socket: {}", error);
+			}
+		});
 	}
+	// Start the DBus Server
+	{
+		let ready_sender = ready_sender.clone();
+		async_std::task::spawn(async move {
+			if let Err(error) = DbusServer::init(
+				sender,
+				app_sender,
+				osd_receiver,
+				settings_receiver,
+				ready_sender,
+			)
+			.await
+			{
+				eprintln!("Could not start the DBus server: {}", error);
+				// The service is only ready with the bus name, so don't leave systemd waiting.
+				// Otherwise the unix socket keeps working without a session bus
+				if systemd::is_notify_service() {
+					std::process::exit(1);
+				}
+			}
+		});
+	}
+	// Tell systemd that the server is ready
+	async_std::task::spawn(async move {
+		for _ in 0..2 {
+			if ready_receiver.recv().await.is_err() {
+				return;
+			}
+		}
+		if let Err(error) = systemd::notify_ready() {
+			eprintln!("Could not notify systemd: {}", error);
+		}
+	});
+	// Start the GTK Application
+	std::process::exit(
+		SwayOSDApplication::new(
+			server_config,
+			args,
+			receiver,
+			app_receiver,
+			osd_sender,
+			settings_sender,
+		)
+		.start(ready_sender),
+	);
 }
 
-const GRESOURCE_BASE_PATH: &str = "/org/erikreider/swayosd";
-
-fn main() {
+/// Initializes GTK and loads the bundled icons and the default stylesheet
+fn init_gtk() {
 	if gtk::init().is_err() {
 		eprintln!("failed to initialize GTK Application");
 		std::process::exit(1);
@@ -112,34 +216,4 @@ fn main() {
 		}
 		None => eprintln!("Could not find the system CSS file..."),
 	}
-
-	let args = Arc::new(args::ArgsServer::parse());
-
-	// Parse Config
-	let server_config = Arc::new(
-		config::user::read_user_config(args.config.as_deref())
-			.expect("Failed to parse config file")
-			.server,
-	);
-
-	// Try loading the users CSS theme
-	if let Some(user_config_path) = user_style_path(args.style.clone()) {
-		let user_provider = CssProvider::new();
-		user_provider.connect_parsing_error(|_provider, _section, error| {
-			eprintln!("Failed loading user defined style.css: {}", error);
-		});
-		user_provider.load_from_path(&user_config_path);
-		gtk::style_context_add_provider_for_display(
-			&display,
-			&user_provider,
-			gtk::STYLE_PROVIDER_PRIORITY_USER,
-		);
-		println!("Loaded user defined CSS file");
-	}
-
-	let (sender, receiver) = async_channel::bounded::<(ArgTypes, String)>(1);
-	// Start the DBus Server
-	async_std::task::spawn(DbusServer::init(sender));
-	// Start the GTK Application
-	std::process::exit(SwayOSDApplication::new(server_config, args, receiver).start());
 }
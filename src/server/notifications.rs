@@ -0,0 +1,256 @@
+use std::{
+	collections::HashMap,
+	future::pending,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+use async_channel::Sender;
+use async_std::{stream::StreamExt, task};
+use zbus::{
+	connection,
+	fdo::{self, DBusProxy, RequestNameFlags, RequestNameReply},
+	interface,
+	object_server::{InterfaceRef, SignalEmitter},
+	proxy,
+	zvariant::{OwnedValue, Value},
+};
+
+use crate::actions::{send_actions, ActionRequest};
+use crate::argtypes::ArgTypes;
+use crate::utils::value_to_f64;
+
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Hints that mark a notification as an OSD
+const OSD_HINTS: [&str; 3] = ["value", "synchronous", "x-canonical-private-synchronous"];
+
+/// The notification daemon that owned the name before SwayOSD
+#[proxy(
+	interface = "org.freedesktop.Notifications",
+	default_path = "/org/freedesktop/Notifications"
+)]
+trait NotificationDaemon {
+	#[allow(clippy::too_many_arguments)]
+	fn notify(
+		&self,
+		app_name: &str,
+		replaces_id: u32,
+		app_icon: &str,
+		summary: &str,
+		body: &str,
+		actions: &[&str],
+		hints: &HashMap<&str, &Value<'_>>,
+		expire_timeout: i32,
+	) -> zbus::Result<u32>;
+
+	fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+	fn get_capabilities(&self) -> zbus::Result<Vec<String>>;
+
+	fn get_server_information(&self) -> zbus::Result<(String, String, String, String)>;
+
+	#[zbus(signal)]
+	fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+	#[zbus(signal)]
+	fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+}
+
+/// Displays notifications with OSD hints and passes all others to the real daemon
+pub struct NotificationsBridge {
+	sender: Sender<ActionRequest>,
+	daemon: Option<NotificationDaemonProxy<'static>>,
+	/// IDs of the OSD notifications, kept apart from the IDs of the real daemon
+	next_id: AtomicU32,
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl NotificationsBridge {
+	#[allow(clippy::too_many_arguments)]
+	async fn notify(
+		&self,
+		app_name: String,
+		replaces_id: u32,
+		app_icon: String,
+		summary: String,
+		body: String,
+		actions: Vec<String>,
+		hints: HashMap<String, OwnedValue>,
+		expire_timeout: i32,
+	) -> fdo::Result<u32> {
+		if !OSD_HINTS.iter().any(|hint| hints.contains_key(*hint)) {
+			let actions: Vec<&str> = actions.iter().map(String::as_str).collect();
+			let hints: HashMap<&str, &Value<'_>> = hints
+				.iter()
+				.map(|(key, value)| (key.as_str(), &**value))
+				.collect();
+			return Ok(self
+				.daemon()?
+				.notify(
+					&app_name,
+					replaces_id,
+					&app_icon,
+					&summary,
+					&body,
+					&actions,
+					&hints,
+					expire_timeout,
+				)
+				.await?);
+		}
+
+		let mut osd_actions = Vec::new();
+		if !app_icon.is_empty() {
+			osd_actions.push((ArgTypes::CustomIcon, Some(app_icon)));
+		}
+		let text = if summary.is_empty() { body } else { summary };
+		match hints.get("value").and_then(|value| value_to_f64(value)) {
+			Some(value) => {
+				if !text.is_empty() {
+					osd_actions.push((ArgTypes::CustomProgressText, Some(text)));
+				}
+				let fraction = value / 100.0;
+				osd_actions.push((ArgTypes::CustomProgress, Some(fraction.to_string())));
+			}
+			None => osd_actions.push((ArgTypes::CustomMessage, Some(text))),
+		}
+		send_actions(&self.sender, osd_actions).await?;
+
+		match replaces_id {
+			0 => Ok(self.next_id.fetch_add(1, Ordering::Relaxed)),
+			id => Ok(id),
+		}
+	}
+
+	async fn close_notification(&self, id: u32) -> fdo::Result<()> {
+		match &self.daemon {
+			Some(daemon) => Ok(daemon.close_notification(id).await?),
+			None => Ok(()),
+		}
+	}
+
+	async fn get_capabilities(&self) -> fdo::Result<Vec<String>> {
+		match &self.daemon {
+			Some(daemon) => Ok(daemon.get_capabilities().await?),
+			None => Ok(vec!["body".to_owned()]),
+		}
+	}
+
+	async fn get_server_information(&self) -> fdo::Result<(String, String, String, String)> {
+		match &self.daemon {
+			Some(daemon) => Ok(daemon.get_server_information().await?),
+			None => Ok((
+				"SwayOSD".to_owned(),
+				"ErikReider".to_owned(),
+				env!("CARGO_PKG_VERSION").to_owned(),
+				"1.2".to_owned(),
+			)),
+		}
+	}
+
+	#[zbus(signal)]
+	async fn notification_closed(
+		emitter: &SignalEmitter<'_>,
+		id: u32,
+		reason: u32,
+	) -> zbus::Result<()>;
+
+	#[zbus(signal)]
+	async fn action_invoked(
+		emitter: &SignalEmitter<'_>,
+		id: u32,
+		action_key: &str,
+	) -> zbus::Result<()>;
+}
+
+impl NotificationsBridge {
+	/// Takes over org.freedesktop.Notifications from the running notification daemon,
+	/// which has to allow being replaced
+	pub async fn init(sender: Sender<ActionRequest>) -> zbus::Result<()> {
+		let connection = connection::Builder::session()?.build().await?;
+
+		// Remember the real daemon before it loses the name
+		let daemon = match DBusProxy::new(&connection)
+			.await?
+			.get_name_owner(NOTIFICATIONS_NAME.try_into()?)
+			.await
+		{
+			Ok(owner) => Some(
+				NotificationDaemonProxy::builder(&connection)
+					.destination(owner)?
+					.build()
+					.await?,
+			),
+			Err(_) => None,
+		};
+
+		let bridge = NotificationsBridge {
+			sender,
+			daemon: daemon.clone(),
+			next_id: AtomicU32::new(u32::MAX / 2),
+		};
+		connection
+			.object_server()
+			.at(NOTIFICATIONS_PATH, bridge)
+			.await?;
+		let reply = connection
+			.request_name_with_flags(
+				NOTIFICATIONS_NAME,
+				RequestNameFlags::ReplaceExisting | RequestNameFlags::DoNotQueue,
+			)
+			.await?;
+		if !matches!(
+			reply,
+			RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner
+		) {
+			return Err(zbus::Error::Failure(format!(
+				"The running notification daemon doesn't allow replacing {}",
+				NOTIFICATIONS_NAME
+			)));
+		}
+
+		// Forward the signals of the real daemon to the clients
+		if let Some(daemon) = daemon {
+			let iface_ref = connection
+				.object_server()
+				.interface::<_, NotificationsBridge>(NOTIFICATIONS_PATH)
+				.await?;
+			task::spawn(Self::forward_closed(daemon.clone(), iface_ref.clone()));
+			task::spawn(Self::forward_action_invoked(daemon, iface_ref));
+		}
+
+		pending::<()>().await;
+		Ok(())
+	}
+
+	fn daemon(&self) -> fdo::Result<&NotificationDaemonProxy<'static>> {
+		self.daemon.as_ref().ok_or_else(|| {
+			fdo::Error::Failed("There's no notification daemon to forward to".to_owned())
+		})
+	}
+
+	async fn forward_closed(
+		daemon: NotificationDaemonProxy<'static>,
+		iface_ref: InterfaceRef<NotificationsBridge>,
+	) -> zbus::Result<()> {
+		let mut stream = daemon.receive_notification_closed().await?;
+		while let Some(signal) = stream.next().await {
+			let args = signal.args()?;
+			Self::notification_closed(iface_ref.signal_emitter(), args.id, args.reason).await?;
+		}
+		Ok(())
+	}
+
+	async fn forward_action_invoked(
+		daemon: NotificationDaemonProxy<'static>,
+		iface_ref: InterfaceRef<NotificationsBridge>,
+	) -> zbus::Result<()> {
+		let mut stream = daemon.receive_action_invoked().await?;
+		while let Some(signal) = stream.next().await {
+			let args = signal.args()?;
+			Self::action_invoked(iface_ref.signal_emitter(), args.id, &args.action_key).await?;
+		}
+		Ok(())
+	}
+}
@@ -7,27 +7,135 @@ use gtk::{
 	glib::{self, clone},
 	prelude::*,
 };
-use pulsectl::controllers::types::DeviceInfo;
 
+use crate::renderer::OsdRenderer;
+use crate::state::OsdEvent;
 use crate::widgets::segmented_progress_widget::SegmentedProgressWidget;
 use crate::{
 	brightness_backend::BrightnessBackend,
-	utils::{
-		get_max_volume, get_show_percentage, get_top_margin, volume_to_f64, KeysLocks,
-		VolumeDeviceType,
-	},
+	utils::{get_show_percentage, get_top_margin, KeysLocks, VolumeDeviceType},
+	volume_backend::VolumeDevice,
 };
 
 use gtk_layer_shell::LayerShell;
 
 const ICON_SIZE: i32 = 32;
 
+pub fn volume_icon_name(volume: f64, muted: bool, is_source: bool) -> String {
+	let icon_prefix = match is_source {
+		false => "sink",
+		true => "source",
+	};
+	let icon_state = &match (muted, volume) {
+		(true, _) => "muted",
+		(_, 0.0) => "muted",
+		(false, x) if x > 0.0 && x <= 33.0 => "low",
+		(false, x) if x > 33.0 && x <= 66.0 => "medium",
+		(false, x) if x > 66.0 && x <= 100.0 => "high",
+		(false, x) if x > 100.0 => match is_source {
+			false => "high",
+			true => "overamplified",
+		},
+		(_, _) => "high",
+	};
+	format!("{}-volume-{}-symbolic", icon_prefix, icon_state)
+}
+
+pub fn kbd_backlight_icon_name(value: u32, max: u32) -> &'static str {
+	match value.min(max) {
+		0 => "keyboard-brightness-off-symbolic",
+		v if (v == max) => "keyboard-brightness-high-symbolic",
+		_ => "keyboard-brightness-medium-symbolic",
+	}
+}
+
+pub fn keylock_label_and_icon_name(key: &KeysLocks, state: bool) -> (String, &'static str) {
+	let on_off_text = match state {
+		true => "On",
+		false => "Off",
+	};
+
+	match key {
+		KeysLocks::CapsLock => {
+			let symbol = "caps-lock-symbolic";
+			let text = "Caps Lock ".to_string() + on_off_text;
+			(text, symbol)
+		}
+		KeysLocks::NumLock => {
+			let symbol = "num-lock-symbolic";
+			let text = "Num Lock ".to_string() + on_off_text;
+			(text, symbol)
+		}
+		KeysLocks::ScrollLock => {
+			let symbol = "scroll-lock-symbolic";
+			let text = "Scroll Lock ".to_string() + on_off_text;
+			(text, symbol)
+		}
+	}
+}
+
+fn update_margins(window: &gtk::ApplicationWindow, monitor: &gdk::Monitor) {
+	// Monitor scale factor is not always correct
+	// Transform monitor height into coordinate system of window
+	let mon_height = monitor.geometry().height() / window.scale_factor();
+	// Calculate margin from bottom while preserving top_margin semantics:
+	// top_margin=0.85 means window should be at 85% from top, which equals
+	// 15% from bottom. By anchoring to bottom, we avoid issues with
+	// window.allocated_height() being 0 or incorrect during initialization.
+	let margin = (mon_height as f32 * (1.0 - get_top_margin())).round() as i32;
+	window.set_margin(gtk_layer_shell::Edge::Bottom, margin);
+}
+
+/// The widgets of a displayed volume or brightness OSD, updated in place by the next change
+#[derive(Clone, Debug)]
+struct LevelWidgets {
+	kind: &'static str,
+	icon: gtk::Image,
+	progress: gtk::ProgressBar,
+	label: gtk::Label,
+	show_percentage: bool,
+}
+
+/// The value of a tracked progress
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobProgress {
+	Fraction(f64),
+	Segmented(u32, u32),
+}
+
+impl JobProgress {
+	/// The kind of the OsdShown signal
+	pub fn kind(&self) -> &'static str {
+		match self {
+			JobProgress::Fraction(_) => "custom-progress",
+			JobProgress::Segmented(..) => "custom-segmented-progress",
+		}
+	}
+}
+
+/// A custom progress with a client chosen ID, updated in place until it's closed
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressJob {
+	pub id: String,
+	pub progress: JobProgress,
+	pub text: Option<String>,
+	pub icon_name: Option<String>,
+	pub monitor_name: Option<String>,
+	/// Displayed without a timeout, and again after other OSDs time out
+	pub persistent: bool,
+}
+
 /// A window that our application can open that contains the main project view.
 #[derive(Clone, Debug)]
 pub struct SwayosdWindow {
 	pub window: gtk::ApplicationWindow,
 	pub monitor: gdk::Monitor,
 	container: gtk::Box,
+	level: Rc<RefCell<Option<LevelWidgets>>>,
+	/// The ID of the displayed progress job
+	job_id: Rc<RefCell<Option<String>>>,
+	/// Displayed whenever no other OSD is
+	persistent_job: Rc<RefCell<Option<ProgressJob>>>,
 	timeout_id: Rc<RefCell<Option<glib::SourceId>>>,
 }
 
@@ -68,18 +176,6 @@ impl SwayosdWindow {
 			}
 		});
 
-		let update_margins = |window: &gtk::ApplicationWindow, monitor: &gdk::Monitor| {
-			// Monitor scale factor is not always correct
-			// Transform monitor height into coordinate system of window
-			let mon_height = monitor.geometry().height() / window.scale_factor();
-			// Calculate margin from bottom while preserving top_margin semantics:
-			// top_margin=0.85 means window should be at 85% from top, which equals
-			// 15% from bottom. By anchoring to bottom, we avoid issues with
-			// window.allocated_height() being 0 or incorrect during initialization.
-			let margin = (mon_height as f32 * (1.0 - get_top_margin())).round() as i32;
-			window.set_margin(gtk_layer_shell::Edge::Bottom, margin);
-		};
-
 		// Set the window margin
 		update_margins(&window, monitor);
 		// Ensure window margin is updated when necessary
@@ -103,6 +199,9 @@ impl SwayosdWindow {
 			window,
 			container,
 			monitor: monitor.clone(),
+			level: Rc::new(RefCell::new(None)),
+			job_id: Rc::new(RefCell::new(None)),
+			persistent_job: Rc::new(RefCell::new(None)),
 			timeout_id: Rc::new(RefCell::new(None)),
 		}
 	}
@@ -111,66 +210,55 @@ impl SwayosdWindow {
 		self.window.close();
 	}
 
-	pub fn changed_volume(&self, device: &DeviceInfo, device_type: &VolumeDeviceType) {
-		self.clear_osd();
-
-		let volume = volume_to_f64(&device.volume.avg());
-		let icon_prefix = match device_type {
-			VolumeDeviceType::Sink(_) => "sink",
-			VolumeDeviceType::Source(_) => "source",
-		};
-		let icon_state = &match (device.mute, volume) {
-			(true, _) => "muted",
-			(_, 0.0) => "muted",
-			(false, x) if x > 0.0 && x <= 33.0 => "low",
-			(false, x) if x > 33.0 && x <= 66.0 => "medium",
-			(false, x) if x > 66.0 && x <= 100.0 => "high",
-			(false, x) if x > 100.0 => match device_type {
-				VolumeDeviceType::Sink(_) => "high",
-				VolumeDeviceType::Source(_) => "overamplified",
-			},
-			(_, _) => "high",
-		};
-		let icon_name = &format!("{}-volume-{}-symbolic", icon_prefix, icon_state);
-
-		let max_volume: f64 = get_max_volume().into();
-
-		let icon = self.build_icon_widget(icon_name);
-		let progress = self.build_progress_widget(volume / max_volume);
-		let label = self.build_text_widget(Some(&format!("{}%", volume)), Some(4));
-
-		progress.set_sensitive(!device.mute);
-
-		self.container.append(&icon);
-		self.container.append(&progress);
-		if get_show_percentage() {
-			self.container.append(&label);
+	/// Moves the window and shows or hides the percentage after the settings changed
+	pub fn refresh_settings(&self) {
+		update_margins(&self.window, &self.monitor);
+		// Show or hide the percentage of the displayed level
+		let show_percentage = get_show_percentage();
+		if let Some(level) = self.level.borrow_mut().as_mut()
+			&& level.show_percentage != show_percentage
+		{
+			if show_percentage {
+				self.container.append(&level.label);
+			} else {
+				self.container.remove(&level.label);
+			}
+			level.show_percentage = show_percentage;
 		}
+	}
 
-		self.run_timeout();
+	pub fn changed_volume(
+		&self,
+		device: &VolumeDevice,
+		device_type: VolumeDeviceType,
+		max_volume: u8,
+	) {
+		let volume = device.volume();
+		let is_source = device_type == VolumeDeviceType::Source;
+		let icon_name = &volume_icon_name(volume, device.muted, is_source);
+
+		let max_volume: f64 = max_volume.into();
+
+		self.show_level(
+			if is_source { "source" } else { "sink" },
+			icon_name,
+			volume / max_volume,
+			&format!("{}%", volume),
+			!device.muted,
+		);
 	}
 
 	pub fn changed_brightness(&self, brightness_backend: &mut dyn BrightnessBackend) {
-		self.clear_osd();
-
-		let icon_name = "display-brightness-symbolic";
-		let icon = self.build_icon_widget(icon_name);
-
 		let brightness = brightness_backend.get_current() as f64;
 		let max = brightness_backend.get_max() as f64;
-		let progress = self.build_progress_widget(brightness / max);
-		let label = self.build_text_widget(
-			Some(&format!("{}%", (brightness / max * 100.).round() as i32)),
-			Some(4),
-		);
 
-		self.container.append(&icon);
-		self.container.append(&progress);
-		if get_show_percentage() {
-			self.container.append(&label);
-		}
-
-		self.run_timeout();
+		self.show_level(
+			"brightness",
+			"display-brightness-symbolic",
+			brightness / max,
+			&format!("{}%", (brightness / max * 100.).round() as i32),
+			true,
+		);
 	}
 
 	pub fn changed_player(&self, icon: &str, label: Option<&str>) {
@@ -191,12 +279,7 @@ impl SwayosdWindow {
 
 		let value = value.min(max);
 
-		let icon_name = match value {
-			0 => "keyboard-brightness-off-symbolic",
-			v if (v == max) => "keyboard-brightness-high-symbolic",
-			_ => "keyboard-brightness-medium-symbolic",
-		};
-		let icon = self.build_icon_widget(icon_name);
+		let icon = self.build_icon_widget(kbd_backlight_icon_name(value, max));
 		self.container.append(&icon);
 
 		// A segmented progress bar looks cramped when there are too many segments
@@ -204,7 +287,7 @@ impl SwayosdWindow {
 			let progress = self.build_segmented_progress_widget(value, max);
 			self.container.append(&progress);
 		} else {
-			let progress = self.build_progress_widget((value / max) as f64);
+			let progress = self.build_progress_widget(value as f64 / max as f64);
 			self.container.append(&progress);
 		}
 
@@ -217,28 +300,7 @@ impl SwayosdWindow {
 		let label = self.build_text_widget(None, None);
 		label.set_hexpand(true);
 
-		let on_off_text = match state {
-			true => "On",
-			false => "Off",
-		};
-
-		let (label_text, symbol) = match key {
-			KeysLocks::CapsLock => {
-				let symbol = "caps-lock-symbolic";
-				let text = "Caps Lock ".to_string() + on_off_text;
-				(text, symbol)
-			}
-			KeysLocks::NumLock => {
-				let symbol = "num-lock-symbolic";
-				let text = "Num Lock ".to_string() + on_off_text;
-				(text, symbol)
-			}
-			KeysLocks::ScrollLock => {
-				let symbol = "scroll-lock-symbolic";
-				let text = "Scroll Lock ".to_string() + on_off_text;
-				(text, symbol)
-			}
-		};
+		let (label_text, symbol) = keylock_label_and_icon_name(&key, state);
 
 		label.set_text(&label_text);
 		let icon = self.build_icon_widget(symbol);
@@ -252,21 +314,7 @@ impl SwayosdWindow {
 	}
 
 	pub fn custom_progress(&self, fraction: f64, text: Option<String>, icon_name: Option<&str>) {
-		self.clear_osd();
-
-		if let Some(icon_name) = icon_name {
-			let icon = self.build_icon_widget(icon_name);
-			self.container.append(&icon);
-		}
-
-		let progress = self.build_progress_widget(fraction.clamp(0.0, 1.0));
-		self.container.append(&progress);
-
-		if let Some(text) = text {
-			let label = self.build_text_widget(Some(text.deref()), None);
-			self.container.append(&label);
-		}
-
+		self.build_custom_progress(fraction, text, icon_name);
 		self.run_timeout();
 	}
 
@@ -277,23 +325,70 @@ impl SwayosdWindow {
 		text: Option<String>,
 		icon_name: Option<&str>,
 	) {
-		self.clear_osd();
+		self.build_custom_segmented_progress(value, n_segments, text, icon_name);
+		self.run_timeout();
+	}
 
-		if let Some(icon_name) = icon_name {
-			let icon = self.build_icon_widget(icon_name);
-			self.container.append(&icon);
+	/// Displays the progress job, replacing the displayed one with the same ID
+	pub fn show_job(&self, job: &ProgressJob) {
+		self.build_job(job);
+		if job.persistent {
+			self.persistent_job.replace(Some(job.clone()));
+			self.stop_timeout();
+			self.window.show();
+		} else {
+			let mut persistent_job = self.persistent_job.borrow_mut();
+			if persistent_job
+				.as_ref()
+				.is_some_and(|other| other.id == job.id)
+			{
+				*persistent_job = None;
+			}
+			drop(persistent_job);
+			self.run_timeout();
 		}
+	}
 
-		let value = value.min(n_segments);
-		let progress = self.build_segmented_progress_widget(value, n_segments);
-		self.container.append(&progress);
-
-		if let Some(text) = text {
-			let label = self.build_text_widget(Some(text.deref()), None);
-			self.container.append(&label);
+	/// Hides the progress job if it's displayed, or shows the persistent job instead
+	pub fn close_job(&self, id: &str) {
+		if self.job_id.borrow().as_deref() == Some(id) {
+			self.stop_timeout();
+			// Go back to the persistent job, unless it's the closed one
+			let persistent_job = self
+				.persistent_job
+				.borrow()
+				.clone()
+				.filter(|job| job.id != id);
+			match persistent_job {
+				Some(job) => self.build_job(&job),
+				None => {
+					self.clear_osd();
+					self.window.hide();
+				}
+			}
 		}
+	}
 
-		self.run_timeout();
+	/// Sets the job that's displayed whenever no other OSD is
+	pub fn set_persistent_job(&self, job: Option<ProgressJob>) {
+		if *self.persistent_job.borrow() == job {
+			return;
+		}
+		self.persistent_job.replace(job.clone());
+		// Another OSD is displayed until its timeout runs out
+		if self.timeout_id.borrow().is_some() {
+			return;
+		}
+		match job {
+			Some(job) => {
+				self.build_job(&job);
+				self.window.show();
+			}
+			None => {
+				self.clear_osd();
+				self.window.hide();
+			}
+		}
 	}
 
 	pub fn custom_message(&self, message: &str, icon_name: Option<&str>) {
@@ -322,8 +417,111 @@ impl SwayosdWindow {
 		self.run_timeout();
 	}
 
+	/// Updates the widgets of the same kind of OSD if it's already displayed,
+	/// so repeated changes don't rebuild the widgets
+	fn show_level(
+		&self,
+		kind: &'static str,
+		icon_name: &str,
+		fraction: f64,
+		percentage: &str,
+		sensitive: bool,
+	) {
+		let show_percentage = get_show_percentage();
+		let displayed = self
+			.level
+			.borrow()
+			.clone()
+			.filter(|level| level.kind == kind && level.show_percentage == show_percentage);
+		let level = match displayed {
+			Some(level) => {
+				let icon = gtk::gio::ThemedIcon::from_names(&[icon_name, "missing-symbolic"]);
+				level.icon.set_from_gicon(&icon);
+				level
+			}
+			None => {
+				self.clear_osd();
+				let level = LevelWidgets {
+					kind,
+					icon: self.build_icon_widget(icon_name),
+					progress: self.build_progress_widget(fraction),
+					label: self.build_text_widget(None, Some(4)),
+					show_percentage,
+				};
+				self.container.append(&level.icon);
+				self.container.append(&level.progress);
+				if show_percentage {
+					self.container.append(&level.label);
+				}
+				self.level.replace(Some(level.clone()));
+				level
+			}
+		};
+		level.progress.set_fraction(fraction);
+		level.progress.set_sensitive(sensitive);
+		level.label.set_text(percentage);
+
+		self.run_timeout();
+	}
+
+	fn build_custom_progress(&self, fraction: f64, text: Option<String>, icon_name: Option<&str>) {
+		self.clear_osd();
+
+		if let Some(icon_name) = icon_name {
+			let icon = self.build_icon_widget(icon_name);
+			self.container.append(&icon);
+		}
+
+		let progress = self.build_progress_widget(fraction.clamp(0.0, 1.0));
+		self.container.append(&progress);
+
+		if let Some(text) = text {
+			let label = self.build_text_widget(Some(text.deref()), None);
+			self.container.append(&label);
+		}
+	}
+
+	fn build_custom_segmented_progress(
+		&self,
+		value: u32,
+		n_segments: u32,
+		text: Option<String>,
+		icon_name: Option<&str>,
+	) {
+		self.clear_osd();
+
+		if let Some(icon_name) = icon_name {
+			let icon = self.build_icon_widget(icon_name);
+			self.container.append(&icon);
+		}
+
+		let value = value.min(n_segments);
+		let progress = self.build_segmented_progress_widget(value, n_segments);
+		self.container.append(&progress);
+
+		if let Some(text) = text {
+			let label = self.build_text_widget(Some(text.deref()), None);
+			self.container.append(&label);
+		}
+	}
+
+	fn build_job(&self, job: &ProgressJob) {
+		let icon_name = job.icon_name.as_deref();
+		match job.progress {
+			JobProgress::Fraction(fraction) => {
+				self.build_custom_progress(fraction, job.text.clone(), icon_name)
+			}
+			JobProgress::Segmented(value, n_segments) => {
+				self.build_custom_segmented_progress(value, n_segments, job.text.clone(), icon_name)
+			}
+		}
+		self.job_id.replace(Some(job.id.clone()));
+	}
+
 	/// Clear all container children
 	fn clear_osd(&self) {
+		self.level.replace(None);
+		self.job_id.replace(None);
 		let mut next = self.container.first_child();
 		while let Some(widget) = next {
 			next = widget.next_sibling();
@@ -331,17 +529,25 @@ impl SwayosdWindow {
 		}
 	}
 
-	fn run_timeout(&self) {
-		// Hide window after timeout
+	fn stop_timeout(&self) {
 		if let Some(timeout_id) = self.timeout_id.take() {
 			timeout_id.remove()
 		}
+	}
+
+	fn run_timeout(&self) {
+		// Hide window after timeout, or go back to the persistent job
+		self.stop_timeout();
 		let s = self.clone();
 		self.timeout_id.replace(Some(glib::timeout_add_local_once(
 			Duration::from_millis(1000),
 			move || {
-				s.window.hide();
 				s.timeout_id.replace(None);
+				let persistent_job = s.persistent_job.borrow().clone();
+				match persistent_job {
+					Some(job) => s.build_job(&job),
+					None => s.window.hide(),
+				}
 			},
 		)));
 
@@ -390,3 +596,71 @@ impl SwayosdWindow {
 		}
 	}
 }
+
+impl OsdRenderer for SwayosdWindow {
+	fn matches_monitor(&self, monitor_name: &str) -> bool {
+		self.monitor
+			.connector()
+			.is_some_and(|connector| connector == monitor_name)
+	}
+
+	fn close(&self) {
+		SwayosdWindow::close(self)
+	}
+
+	fn refresh_settings(&self) {
+		SwayosdWindow::refresh_settings(self)
+	}
+
+	fn osd_shown(&self, _event: &OsdEvent) {}
+
+	fn changed_volume(&self, device: &VolumeDevice, device_type: VolumeDeviceType, max_volume: u8) {
+		SwayosdWindow::changed_volume(self, device, device_type, max_volume)
+	}
+
+	fn changed_brightness(&self, brightness_backend: &mut dyn BrightnessBackend) {
+		SwayosdWindow::changed_brightness(self, brightness_backend)
+	}
+
+	fn changed_player(&self, icon: &str, label: Option<&str>) {
+		SwayosdWindow::changed_player(self, icon, label)
+	}
+
+	fn changed_kbd_backlight(&self, value: u32, max: u32) {
+		SwayosdWindow::changed_kbd_backlight(self, value, max)
+	}
+
+	fn changed_keylock(&self, key: KeysLocks, state: bool) {
+		SwayosdWindow::changed_keylock(self, key, state)
+	}
+
+	fn custom_progress(&self, fraction: f64, text: Option<String>, icon_name: Option<&str>) {
+		SwayosdWindow::custom_progress(self, fraction, text, icon_name)
+	}
+
+	fn custom_segmented_progress(
+		&self,
+		value: u32,
+		n_segments: u32,
+		text: Option<String>,
+		icon_name: Option<&str>,
+	) {
+		SwayosdWindow::custom_segmented_progress(self, value, n_segments, text, icon_name)
+	}
+
+	fn custom_message(&self, message: &str, icon_name: Option<&str>) {
+		SwayosdWindow::custom_message(self, message, icon_name)
+	}
+
+	fn show_job(&self, job: &ProgressJob) {
+		SwayosdWindow::show_job(self, job)
+	}
+
+	fn close_job(&self, id: &str) {
+		SwayosdWindow::close_job(self, id)
+	}
+
+	fn set_persistent_job(&self, job: Option<ProgressJob>) {
+		SwayosdWindow::set_persistent_job(self, job)
+	}
+}
@@ -0,0 +1,68 @@
+use async_std::task;
+
+use crate::argtypes::ArgTypes;
+use crate::brightness_backend;
+use crate::error::ActionError;
+use crate::state::{BrightnessState, VolumeState};
+use crate::utils::{
+	brightness_state, get_audio_backend, get_default_max_volume, get_device_volume,
+	get_key_lock_state, volume_state, KeysLocks, VolumeDeviceType,
+};
+use crate::volume_backend::get_preferred_backend;
+
+/// Device kind is one of sink|source. Doesn't display the OSD
+pub async fn query_volume(
+	device_kind: &str,
+	device: Option<String>,
+) -> Result<VolumeState, ActionError> {
+	let is_source = match device_kind {
+		"sink" => false,
+		"source" => true,
+		kind => {
+			return Err(ActionError::InvalidValue(format!(
+				"Unknown device kind: \"{}\"",
+				kind
+			)));
+		}
+	};
+	task::spawn_blocking(move || {
+		let device_type = if is_source {
+			VolumeDeviceType::Source
+		} else {
+			VolumeDeviceType::Sink
+		};
+		let mut backend = get_preferred_backend(device_type, get_audio_backend())?;
+		let device = get_device_volume(backend.as_mut(), device.as_deref())?;
+		Ok(volume_state(&device, get_default_max_volume()))
+	})
+	.await
+}
+
+/// Doesn't display the OSD
+pub async fn query_brightness(device: Option<String>) -> Result<BrightnessState, ActionError> {
+	task::spawn_blocking(
+		move || match brightness_backend::get_preferred_backend(device) {
+			Ok(mut backend) => Ok(brightness_state(backend.as_mut())),
+			Err(error) => Err(ActionError::BrightnessBackendUnavailable(error.to_string())),
+		},
+	)
+	.await
+}
+
+/// Key is one of caps-lock|num-lock|scroll-lock. Doesn't display the OSD
+pub async fn query_lock_state(key: &str) -> Result<bool, ActionError> {
+	let (_, key) = lock_key(key)?;
+	Ok(task::spawn_blocking(move || get_key_lock_state(key, None)).await)
+}
+
+pub fn lock_key(key: &str) -> Result<(ArgTypes, KeysLocks), ActionError> {
+	match key {
+		"caps-lock" => Ok((ArgTypes::CapsLock, KeysLocks::CapsLock)),
+		"num-lock" => Ok((ArgTypes::NumLock, KeysLocks::NumLock)),
+		"scroll-lock" => Ok((ArgTypes::ScrollLock, KeysLocks::ScrollLock)),
+		key => Err(ActionError::InvalidValue(format!(
+			"Unknown lock key: \"{}\"",
+			key
+		))),
+	}
+}
@@ -0,0 +1,114 @@
+use crate::brightness_backend::BrightnessBackend;
+use crate::osd_window::ProgressJob;
+use crate::state::OsdEvent;
+use crate::utils::{KeysLocks, VolumeDeviceType};
+use crate::volume_backend::VolumeDevice;
+
+/// Displays the OSDs chosen by the application.
+/// Implemented by the layer-shell window of each monitor and by the headless `LogRenderer`
+pub trait OsdRenderer {
+	/// Whether the OSDs sent to the monitor are displayed by this renderer
+	fn matches_monitor(&self, monitor_name: &str) -> bool;
+
+	fn close(&self);
+
+	/// Applies the changed top margin and percentage setting to the displayed OSD
+	fn refresh_settings(&self);
+
+	/// Called after an OSD was displayed with the event of the `OsdShown` signal
+	fn osd_shown(&self, event: &OsdEvent);
+
+	fn changed_volume(&self, device: &VolumeDevice, device_type: VolumeDeviceType, max_volume: u8);
+
+	fn changed_brightness(&self, brightness_backend: &mut dyn BrightnessBackend);
+
+	fn changed_player(&self, icon: &str, label: Option<&str>);
+
+	fn changed_kbd_backlight(&self, value: u32, max: u32);
+
+	fn changed_keylock(&self, key: KeysLocks, state: bool);
+
+	fn custom_progress(&self, fraction: f64, text: Option<String>, icon_name: Option<&str>);
+
+	fn custom_segmented_progress(
+		&self,
+		value: u32,
+		n_segments: u32,
+		text: Option<String>,
+		icon_name: Option<&str>,
+	);
+
+	fn custom_message(&self, message: &str, icon_name: Option<&str>);
+
+	/// Displays the progress job, replacing the displayed one with the same ID
+	fn show_job(&self, job: &ProgressJob);
+
+	/// Hides the progress job if it's displayed
+	fn close_job(&self, id: &str);
+
+	/// Sets the job that's displayed whenever no other OSD is
+	fn set_persistent_job(&self, job: Option<ProgressJob>);
+}
+
+/// Prints every OSD as a line on stdout instead of displaying it, the same line as
+/// `swayosd-client watch`. Used to run the server without a Wayland display
+pub struct LogRenderer {
+	json: bool,
+}
+
+impl LogRenderer {
+	pub fn new(json: bool) -> Self {
+		Self { json }
+	}
+}
+
+impl OsdRenderer for LogRenderer {
+	fn matches_monitor(&self, _monitor_name: &str) -> bool {
+		true
+	}
+
+	fn close(&self) {}
+
+	fn refresh_settings(&self) {}
+
+	fn osd_shown(&self, event: &OsdEvent) {
+		if !self.json {
+			return println!("{}", event);
+		}
+		match serde_json::to_string(event) {
+			Ok(line) => println!("{}", line),
+			Err(error) => eprintln!("Could not serialize the OSD: {}", error),
+		}
+	}
+
+	// The OSDs are printed from their OsdShown event
+	fn changed_volume(&self, _device: &VolumeDevice, _device_type: VolumeDeviceType, _max: u8) {}
+
+	fn changed_brightness(&self, _brightness_backend: &mut dyn BrightnessBackend) {}
+
+	fn changed_player(&self, _icon: &str, _label: Option<&str>) {}
+
+	fn changed_kbd_backlight(&self, _value: u32, _max: u32) {}
+
+	fn changed_keylock(&self, _key: KeysLocks, _state: bool) {}
+
+	fn custom_progress(&self, _fraction: f64, _text: Option<String>, _icon_name: Option<&str>) {}
+
+	fn custom_segmented_progress(
+		&self,
+		_value: u32,
+		_n_segments: u32,
+		_text: Option<String>,
+		_icon_name: Option<&str>,
+	) {
+	}
+
+	fn custom_message(&self, _message: &str, _icon_name: Option<&str>) {}
+
+	fn show_job(&self, _job: &ProgressJob) {}
+
+	// Nothing stays displayed, so there's nothing to hide or restore
+	fn close_job(&self, _id: &str) {}
+
+	fn set_persistent_job(&self, _job: Option<ProgressJob>) {}
+}
@@ -0,0 +1,121 @@
+use std::{fs, io, path::Path, str::FromStr};
+
+use async_channel::Sender;
+use async_std::{
+	io::{prelude::BufReadExt, BufReader, WriteExt},
+	os::unix::net::{UnixListener, UnixStream},
+	stream::StreamExt,
+	task,
+};
+
+use crate::actions::{request_actions, ActionRequest};
+use crate::argtypes::ArgTypes;
+use crate::error::ActionError;
+use crate::ipc::{socket_path, SocketReply, SocketRequest};
+use crate::queries::{query_brightness, query_lock_state, query_volume};
+use crate::state::KeyLockState;
+
+/// Accepts the same requests as the D-Bus interface for systems without a session bus.
+/// Every line sent by a client is a JSON `SocketRequest` answered by one JSON `SocketReply` line
+pub struct SocketServer;
+
+impl SocketServer {
+	pub async fn init(sender: Sender<ActionRequest>) -> io::Result<()> {
+		let path = socket_path();
+		let listener = bind(&path).await?;
+
+		let mut incoming = listener.incoming();
+		while let Some(stream) = incoming.next().await {
+			match stream {
+				Ok(stream) => {
+					task::spawn(handle_client(sender.clone(), stream));
+				}
+				Err(error) => eprintln!("Socket accept error: {}", error),
+			}
+		}
+		Ok(())
+	}
+}
+
+async fn handle_client(sender: Sender<ActionRequest>, stream: UnixStream) {
+	let mut writer = stream.clone();
+	let mut lines = BufReader::new(stream).lines();
+	while let Some(Ok(line)) = lines.next().await {
+		if line.trim().is_empty() {
+			continue;
+		}
+		let result = match serde_json::from_str::<SocketRequest>(&line) {
+			Ok(request) => handle_request(&sender, request).await,
+			Err(error) => Err(ActionError::InvalidValue(format!(
+				"Invalid request: {}",
+				error
+			))),
+		};
+		let reply = match serde_json::to_string(&SocketReply::from(result)) {
+			Ok(reply) => reply,
+			Err(error) => return eprintln!("Could not serialize the reply: {}", error),
+		};
+		if writer
+			.write_all(format!("{}\n", reply).as_bytes())
+			.await
+			.is_err()
+		{
+			return;
+		}
+	}
+}
+
+async fn handle_request(
+	sender: &Sender<ActionRequest>,
+	request: SocketRequest,
+) -> Result<Option<serde_json::Value>, ActionError> {
+	let state = match request {
+		SocketRequest::Actions(actions) => {
+			let actions = actions
+				.into_iter()
+				.map(|(arg_type, data)| match ArgTypes::from_str(&arg_type) {
+					Ok(arg_type) => Ok((arg_type, data)),
+					Err(_) => Err(ActionError::InvalidValue(format!(
+						"Unknown action: \"{}\"",
+						arg_type
+					))),
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+			return request_actions(sender, actions)
+				.await?
+				.to_json()
+				.map_err(|error| ActionError::Failed(error.to_string()));
+		}
+		SocketRequest::Get { value, device } => match value.as_str() {
+			"output-volume" => serde_json::to_value(query_volume("sink", device).await?),
+			"input-volume" => serde_json::to_value(query_volume("source", device).await?),
+			"brightness" => serde_json::to_value(query_brightness(device).await?),
+			key => {
+				let state = query_lock_state(key).await?;
+				serde_json::to_value(KeyLockState {
+					key: key.to_owned(),
+					state,
+				})
+			}
+		},
+	};
+	match state {
+		Ok(state) => Ok(Some(state)),
+		Err(error) => Err(ActionError::Failed(error.to_string())),
+	}
+}
+
+/// Replaces the socket of a previous server unless it's still running
+async fn bind(path: &Path) -> io::Result<UnixListener> {
+	if UnixStream::connect(path).await.is_ok() {
+		return Err(io::Error::new(
+			io::ErrorKind::AddrInUse,
+			format!("Another server is listening on {}", path.display()),
+		));
+	}
+	match fs::remove_file(path) {
+		Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+		_ => (),
+	}
+	UnixListener::bind(path).await
+}
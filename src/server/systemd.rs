@@ -0,0 +1,27 @@
+use std::{
+	env, io,
+	os::{
+		linux::net::SocketAddrExt,
+		unix::net::{SocketAddr, UnixDatagram},
+	},
+};
+
+/// Whether systemd started the server as a Type=notify service
+pub fn is_notify_service() -> bool {
+	env::var_os("NOTIFY_SOCKET").is_some()
+}
+
+/// Sends READY=1 to systemd when it started the server as a Type=notify service.
+/// Does nothing otherwise
+pub fn notify_ready() -> io::Result<()> {
+	let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+		return Ok(());
+	};
+	let path = path.to_string_lossy().into_owned();
+	let address = match path.strip_prefix('@') {
+		Some(name) => SocketAddr::from_abstract_name(name.as_bytes())?,
+		None => SocketAddr::from_pathname(&path)?,
+	};
+	UnixDatagram::unbound()?.send_to_addr(b"READY=1", &address)?;
+	Ok(())
+}
@@ -4,34 +4,38 @@ use lazy_static::lazy_static;
 use std::{
 	fs::{self, File},
 	io::{prelude::*, BufReader},
+	ops::Range,
 	path::{Path, PathBuf},
 	sync::Mutex,
 };
 
-use pulse::volume::Volume;
-use pulsectl::controllers::{types::DeviceInfo, DeviceControl, SinkController, SourceController};
+use crate::brightness_backend::{self, BrightnessBackend};
+use crate::config::user::AudioBackend;
+use crate::error::ActionError;
+use crate::state::{BrightnessState, VolumeState};
+use crate::volume_backend::{VolumeBackend, VolumeDevice};
+use zbus::zvariant::Value;
 
-use crate::brightness_backend;
-use crate::playerctl::PlayerctlDeviceRaw;
+pub static PRIV_MAX_VOLUME_DEFAULT: u8 = 100_u8;
+pub static PRIV_MIN_BRIGHTNESS_DEFAULT: u32 = 5_u32;
 
-static PRIV_MAX_VOLUME_DEFAULT: u8 = 100_u8;
-static PRIV_MIN_BRIGHTNESS_DEFAULT: u32 = 5_u32;
+/// The top margins accepted from the arguments, the config and D-Bus
+pub const TOP_MARGIN_RANGE: Range<f32> = 0.0..1.0;
+
+/// The step of volume raise and lower without a value
+pub const VOLUME_CHANGE_DELTA: f64 = 5_f64;
+/// The step of brightness raise and lower without a value
+pub const BRIGHTNESS_CHANGE_DELTA: u8 = 5;
 
 lazy_static! {
 	static ref MAX_VOLUME_DEFAULT: Mutex<u8> = Mutex::new(PRIV_MAX_VOLUME_DEFAULT);
-	static ref MAX_VOLUME: Mutex<u8> = Mutex::new(PRIV_MAX_VOLUME_DEFAULT);
 	static ref MIN_BRIGHTNESS_DEFAULT: Mutex<u32> = Mutex::new(PRIV_MIN_BRIGHTNESS_DEFAULT);
-	static ref MIN_BRIGHTNESS: Mutex<u32> = Mutex::new(PRIV_MIN_BRIGHTNESS_DEFAULT);
-	pub static ref DEVICE_NAME_DEFAULT: &'static str = "default";
-	static ref DEVICE_NAME: Mutex<Option<String>> = Mutex::new(None);
-	static ref MONITOR_NAME: Mutex<Option<String>> = Mutex::new(None);
-	pub static ref ICON_NAME_DEFAULT: &'static str = "text-x-generic";
-	static ref ICON_NAME: Mutex<Option<String>> = Mutex::new(None);
-	static ref PROGRESS_TEXT: Mutex<Option<String>> = Mutex::new(None);
-	static ref PLAYER_NAME: Mutex<PlayerctlDeviceRaw> = Mutex::new(PlayerctlDeviceRaw::None);
 	pub static ref TOP_MARGIN_DEFAULT: f32 = 0.85_f32;
 	static ref TOP_MARGIN: Mutex<f32> = Mutex::new(*TOP_MARGIN_DEFAULT);
 	pub static ref SHOW_PERCENTAGE: Mutex<bool> = Mutex::new(false);
+	static ref INHIBITED: Mutex<bool> = Mutex::new(false);
+	static ref INHIBIT_SHOWN_KINDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+	static ref AUDIO_BACKEND: Mutex<AudioBackend> = Mutex::new(AudioBackend::default());
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -50,20 +54,6 @@ pub fn set_default_max_volume(volume: u8) {
 	*vol = volume;
 }
 
-pub fn get_max_volume() -> u8 {
-	*MAX_VOLUME.lock().unwrap()
-}
-
-pub fn set_max_volume(volume: u8) {
-	let mut vol = MAX_VOLUME.lock().unwrap();
-	*vol = volume;
-}
-
-pub fn reset_max_volume() {
-	let mut vol = MAX_VOLUME.lock().unwrap();
-	*vol = *MAX_VOLUME_DEFAULT.lock().unwrap();
-}
-
 pub fn get_default_min_brightness() -> u32 {
 	*MIN_BRIGHTNESS_DEFAULT.lock().unwrap()
 }
@@ -73,20 +63,6 @@ pub fn set_default_min_brightness(brightness: u32) {
 	*min = brightness;
 }
 
-pub fn get_min_brightness() -> u32 {
-	*MIN_BRIGHTNESS.lock().unwrap()
-}
-
-pub fn set_min_brightness(brightness: u32) {
-	let mut min = MIN_BRIGHTNESS.lock().unwrap();
-	*min = brightness;
-}
-
-pub fn reset_min_brightness() {
-	let mut min = MIN_BRIGHTNESS.lock().unwrap();
-	*min = *MIN_BRIGHTNESS_DEFAULT.lock().unwrap();
-}
-
 pub fn get_top_margin() -> f32 {
 	*TOP_MARGIN.lock().unwrap()
 }
@@ -105,75 +81,42 @@ pub fn set_show_percentage(show: bool) {
 	*show_mut = show;
 }
 
-pub fn get_device_name() -> Option<String> {
-	(*DEVICE_NAME.lock().unwrap()).clone()
-}
-
-pub fn set_device_name(name: String) {
-	let mut global_name = DEVICE_NAME.lock().unwrap();
-	*global_name = Some(name);
-}
-
-pub fn reset_device_name() {
-	let mut global_name = DEVICE_NAME.lock().unwrap();
-	*global_name = None;
-}
-
-pub fn get_monitor_name() -> Option<String> {
-	(*MONITOR_NAME.lock().unwrap()).clone()
-}
-
-pub fn set_monitor_name(name: String) {
-	let mut monitor_name = MONITOR_NAME.lock().unwrap();
-	*monitor_name = Some(name);
-}
-
-pub fn reset_monitor_name() {
-	let mut monitor_name = MONITOR_NAME.lock().unwrap();
-	*monitor_name = None;
-}
-
-pub fn get_progress_text() -> Option<String> {
-	(*PROGRESS_TEXT.lock().unwrap()).clone()
-}
-
-pub fn set_progress_text(name: Option<String>) {
-	let mut progress_text = PROGRESS_TEXT.lock().unwrap();
-	*progress_text = name;
-}
-
-pub fn reset_progress_text() {
-	let mut progress_text = PROGRESS_TEXT.lock().unwrap();
-	*progress_text = None;
+pub fn get_inhibited() -> bool {
+	*INHIBITED.lock().unwrap()
 }
 
-pub fn get_icon_name() -> Option<String> {
-	(*ICON_NAME.lock().unwrap()).clone()
+pub fn set_inhibited(inhibited: bool) {
+	let mut inhibited_mut = INHIBITED.lock().unwrap();
+	*inhibited_mut = inhibited;
 }
 
-pub fn set_icon_name(name: String) {
-	let mut icon_name = ICON_NAME.lock().unwrap();
-	*icon_name = Some(name);
+pub fn get_inhibit_shown_kinds() -> Vec<String> {
+	INHIBIT_SHOWN_KINDS.lock().unwrap().clone()
 }
 
-pub fn reset_icon_name() {
-	let mut icon_name = ICON_NAME.lock().unwrap();
-	*icon_name = None;
+pub fn set_inhibit_shown_kinds(kinds: Vec<String>) {
+	let mut kinds_mut = INHIBIT_SHOWN_KINDS.lock().unwrap();
+	*kinds_mut = kinds;
 }
 
-pub fn set_player(name: String) {
-	let mut global_player = PLAYER_NAME.lock().unwrap();
-	*global_player = PlayerctlDeviceRaw::from(name).unwrap_or(PlayerctlDeviceRaw::None);
+pub fn get_audio_backend() -> AudioBackend {
+	*AUDIO_BACKEND.lock().unwrap()
 }
 
-pub fn reset_player() {
-	let mut global_name = PLAYER_NAME.lock().unwrap();
-	*global_name = PlayerctlDeviceRaw::None;
+pub fn set_audio_backend(backend: AudioBackend) {
+	let mut backend_mut = AUDIO_BACKEND.lock().unwrap();
+	*backend_mut = backend;
 }
 
-pub fn get_player() -> PlayerctlDeviceRaw {
-	let player = PLAYER_NAME.lock().unwrap();
-	player.clone()
+/// Whether the OSD of the kind is hidden by the inhibit state.
+/// The kinds are the same as in the OsdShown signal
+pub fn is_osd_inhibited(kind: &str) -> bool {
+	get_inhibited()
+		&& !INHIBIT_SHOWN_KINDS
+			.lock()
+			.unwrap()
+			.iter()
+			.any(|k| k == kind)
 }
 
 pub fn get_key_lock_state(key: KeysLocks, led: Option<String>) -> bool {
@@ -234,9 +177,10 @@ pub enum VolumeChangeType {
 	MuteToggle,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum VolumeDeviceType {
-	Sink(SinkController),
-	Source(SourceController),
+	Sink,
+	Source,
 }
 
 pub enum BrightnessChangeType {
@@ -245,100 +189,146 @@ pub enum BrightnessChangeType {
 	Set,
 }
 
-pub fn volume_to_f64(volume: &Volume) -> f64 {
-	let tmp_vol = f64::from(volume.0 - Volume::MUTED.0);
-	(100.0 * tmp_vol / f64::from(Volume::NORMAL.0 - Volume::MUTED.0)).round()
-}
-
-fn volume_from_f64(volume: f64) -> Volume {
-	let tmp = f64::from(Volume::NORMAL.0 - Volume::MUTED.0) * volume / 100_f64;
-	Volume((tmp + f64::from(Volume::MUTED.0)) as u32)
+/// Gets the sink/source without changing its volume
+pub fn get_device_volume(
+	backend: &mut dyn VolumeBackend,
+	device_name: Option<&str>,
+) -> Result<VolumeDevice, ActionError> {
+	backend.get_device(device_name)
 }
 
 pub fn change_device_volume(
-	device_type: &mut VolumeDeviceType,
+	backend: &mut dyn VolumeBackend,
 	change_type: VolumeChangeType,
 	step: Option<String>,
-) -> Option<DeviceInfo> {
-	// Get the sink/source controller
-	let controller: &mut dyn DeviceControl<DeviceInfo> = match device_type {
-		VolumeDeviceType::Sink(controller) => controller,
-		VolumeDeviceType::Source(controller) => controller,
+	device_name: Option<&str>,
+	max_volume: u8,
+) -> Result<VolumeDevice, ActionError> {
+	let delta = match step.as_deref() {
+		None | Some("") => VOLUME_CHANGE_DELTA,
+		Some(step) => step
+			.parse::<f64>()
+			.ok()
+			.filter(|step| step.is_finite() && *step >= 0.0)
+			.ok_or_else(|| {
+				ActionError::InvalidValue(format!("Invalid volume step: \"{}\"", step))
+			})?,
 	};
 
 	// Get the device
-	let device: DeviceInfo = if let Some(name) = get_device_name()
-		&& let Ok(device) = controller.get_device_by_name(&name)
-	{
-		device
-	} else {
-		match controller.get_default_device() {
-			Ok(device) => device,
-			Err(e) => {
-				eprintln!("Error getting the default device: {}", e);
-				return None;
-			}
-		}
-	};
+	let device = backend.get_device(device_name)?;
 
-	// Adjust the volume / mute state
-	const VOLUME_CHANGE_DELTA: f64 = 5_f64;
-	let delta = volume_from_f64(
-		step.unwrap_or_default()
-			.parse::<f64>()
-			.unwrap_or(VOLUME_CHANGE_DELTA),
-	);
+	// Adjust the volume / mute state of the loudest channel, like pa_cvolume_inc_clamp
+	let volume = device.max_channel_volume();
 	match change_type {
 		VolumeChangeType::Raise => {
-			let max_volume = volume_from_f64(get_max_volume() as f64);
-			if let Some(volume) = device.volume.clone().inc_clamp(delta, max_volume) {
-				controller.set_device_volume_by_index(device.index, volume);
-			}
+			let max_volume = max_volume as f64;
+			backend.set_volume(&device, (volume + delta).min(max_volume))?;
 		}
 		VolumeChangeType::Lower => {
-			if let Some(volume) = device.volume.clone().decrease(delta) {
-				controller.set_device_volume_by_index(device.index, volume);
-			}
+			backend.set_volume(&device, (volume - delta).max(0.0))?;
 		}
 		VolumeChangeType::MuteToggle => {
-			controller.set_device_mute_by_index(device.index, !device.mute);
+			backend.set_mute(&device, !device.muted)?;
 		}
 	}
 
-	match controller.get_device_by_index(device.index) {
-		Ok(device) => Some(device),
-		Err(e) => {
-			eprintln!("Pulse Error: {}", e);
-			None
-		}
+	backend.get_device_by_index(device.index)
+}
+
+pub fn volume_state(device: &VolumeDevice, max_volume: u8) -> VolumeState {
+	VolumeState {
+		device: device.name.clone(),
+		description: device.description.clone(),
+		volume: device.volume(),
+		muted: device.muted,
+		max_volume,
+		channel_volumes: device
+			.channel_volumes
+			.iter()
+			.map(|volume| volume.round())
+			.collect(),
 	}
 }
 
 pub fn change_brightness(
 	change_type: BrightnessChangeType,
 	step: Option<String>,
-) -> brightness_backend::BrightnessBackendResult {
-	let min_brightness = get_min_brightness();
-	const BRIGHTNESS_CHANGE_DELTA: u8 = 5;
-	let value = step.unwrap_or_default().parse::<u8>();
+	device_name: Option<String>,
+	min_brightness: u32,
+) -> Result<Box<dyn BrightnessBackend>, ActionError> {
+	let step = step.unwrap_or_default();
+	let value = step.parse::<u8>();
+	// Only a missing step falls back to the default step
+	if value.is_err() && !step.is_empty() {
+		return Err(ActionError::InvalidValue(format!(
+			"Invalid brightness value: \"{}\"",
+			step
+		)));
+	}
 
-	let mut backend = brightness_backend::get_preferred_backend(get_device_name())?;
+	let mut backend = brightness_backend::get_preferred_backend(device_name)
+		.map_err(|e| ActionError::BrightnessBackendUnavailable(e.to_string()))?;
 
-	match change_type {
+	let result = match change_type {
 		BrightnessChangeType::Raise => backend.raise(
 			value.unwrap_or(BRIGHTNESS_CHANGE_DELTA) as u32,
 			min_brightness,
-		)?,
+		),
 		BrightnessChangeType::Lower => backend.lower(
 			value.unwrap_or(BRIGHTNESS_CHANGE_DELTA) as u32,
 			min_brightness,
-		)?,
-		BrightnessChangeType::Set => backend.set(value? as u32, min_brightness)?,
+		),
+		BrightnessChangeType::Set => match value {
+			Ok(value) => backend.set(value as u32, min_brightness),
+			Err(_) => {
+				return Err(ActionError::InvalidValue(format!(
+					"Invalid brightness value: \"{}\"",
+					step
+				)));
+			}
+		},
 	};
+	result.map_err(|e| ActionError::Failed(e.to_string()))?;
 
 	Ok(backend)
 }
 
+pub fn brightness_state(backend: &mut dyn BrightnessBackend) -> BrightnessState {
+	let value = backend.get_current();
+	let max = backend.get_max();
+	BrightnessState {
+		backend: backend.get_backend_name().to_owned(),
+		device: backend.get_device_name(),
+		value,
+		max,
+		percent: brightness_percent(value, max),
+	}
+}
+
+/// The rounded brightness in percent. Devices without a max brightness are at 0%
+fn brightness_percent(value: u32, max: u32) -> f64 {
+	if max == 0 {
+		return 0.0;
+	}
+	(value as f64 / max as f64 * 100.).round()
+}
+
+/// Numbers in D-Bus dictionaries are sent with different types depending on the sender
+pub fn value_to_f64(value: &Value<'_>) -> Option<f64> {
+	match value {
+		Value::U8(value) => Some(*value as f64),
+		Value::I16(value) => Some(*value as f64),
+		Value::U16(value) => Some(*value as f64),
+		Value::I32(value) => Some(*value as f64),
+		Value::U32(value) => Some(*value as f64),
+		Value::I64(value) => Some(*value as f64),
+		Value::U64(value) => Some(*value as f64),
+		Value::F64(value) => Some(*value),
+		_ => None,
+	}
+}
+
 pub fn get_system_css_path() -> Option<PathBuf> {
 	let mut paths: Vec<PathBuf> = Vec::new();
 	for path in system_config_dirs() {
@@ -367,3 +357,16 @@ pub fn user_style_path(custom_path: Option<PathBuf>) -> Option<String> {
 	}
 	None
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn brightness_percent_rounds_and_handles_a_zero_max() {
+		assert_eq!(brightness_percent(11636, 19393), 60.0);
+		assert_eq!(brightness_percent(19393, 19393), 100.0);
+		assert_eq!(brightness_percent(0, 0), 0.0);
+		assert_eq!(brightness_percent(5, 0), 0.0);
+	}
+}
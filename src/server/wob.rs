@@ -0,0 +1,181 @@
+use std::{
+	collections::HashMap,
+	fs::{self, File},
+	io::{self, BufRead, BufReader},
+	os::unix::fs::FileTypeExt,
+	path::{Path, PathBuf},
+	thread,
+};
+
+use async_channel::Sender;
+use nix::{sys::stat::Mode, unistd::mkfifo};
+
+use crate::actions::ActionRequest;
+use crate::argtypes::ArgTypes;
+use crate::config::user::WobStyle;
+
+/// The value of a full progress bar, same as the default of wob
+const WOB_MAX: f64 = 100.0;
+
+/// Reads wob compatible "<value>" or "<value> <style>" lines from a FIFO
+/// and displays them as a custom progress
+pub struct WobFifo {
+	path: PathBuf,
+	styles: HashMap<String, WobStyle>,
+	sender: Sender<ActionRequest>,
+}
+
+impl WobFifo {
+	pub fn new(
+		path: PathBuf,
+		styles: HashMap<String, WobStyle>,
+		sender: Sender<ActionRequest>,
+	) -> io::Result<Self> {
+		create_fifo(&path)?;
+		Ok(Self {
+			path,
+			styles,
+			sender,
+		})
+	}
+
+	/// Reads the FIFO on a separate thread.
+	/// The FIFO is reopened every time the last writer closes it
+	pub fn start(self) {
+		thread::spawn(move || loop {
+			let file = match File::open(&self.path) {
+				Ok(file) => file,
+				Err(error) => {
+					return eprintln!("Could not open the wob FIFO: {}", error);
+				}
+			};
+			for line in BufReader::new(file).lines() {
+				let line = match line {
+					Ok(line) => line,
+					Err(error) => {
+						eprintln!("Could not read the wob FIFO: {}", error);
+						break;
+					}
+				};
+				if line.trim().is_empty() {
+					continue;
+				}
+				let actions = match self.parse_line(&line) {
+					Ok(actions) => actions,
+					Err(error) => {
+						eprintln!("Invalid wob input \"{}\": {}", line, error);
+						continue;
+					}
+				};
+				let request = ActionRequest {
+					actions,
+					reply: None,
+				};
+				if let Err(error) = self.sender.send_blocking(request) {
+					return eprintln!("Channel Send error: {}", error);
+				}
+			}
+		});
+	}
+
+	fn parse_line(&self, line: &str) -> Result<Vec<(ArgTypes, Option<String>)>, String> {
+		let mut words = line.split_whitespace();
+		let value = words.next().unwrap_or_default();
+		let value = match value.parse::<f64>() {
+			Ok(value) => value,
+			Err(_) => return Err(format!("{} is not a number", value)),
+		};
+
+		let mut actions = Vec::new();
+		// Colors of older wob versions start with a '#' and are ignored
+		if let Some(name) = words.next().filter(|name| !name.starts_with('#')) {
+			let Some(style) = self.styles.get(name) else {
+				return Err(format!("Unknown style \"{}\"", name));
+			};
+			if let Some(icon) = &style.icon {
+				actions.push((ArgTypes::CustomIcon, Some(icon.clone())));
+			}
+			if let Some(text) = &style.text {
+				actions.push((ArgTypes::CustomProgressText, Some(text.clone())));
+			}
+		}
+		actions.push((
+			ArgTypes::CustomProgress,
+			Some((value / WOB_MAX).to_string()),
+		));
+		Ok(actions)
+	}
+}
+
+/// Creates the FIFO unless it already exists
+fn create_fifo(path: &Path) -> io::Result<()> {
+	match fs::metadata(path) {
+		Ok(metadata) if metadata.file_type().is_fifo() => Ok(()),
+		Ok(_) => Err(io::Error::new(
+			io::ErrorKind::AlreadyExists,
+			format!("{} exists but isn't a FIFO", path.display()),
+		)),
+		Err(_) => mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR).map_err(io::Error::from),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn wob_fifo() -> WobFifo {
+		let styles = HashMap::from([(
+			"volume".to_owned(),
+			WobStyle {
+				icon: Some("audio-volume-high-symbolic".to_owned()),
+				text: Some("Volume".to_owned()),
+			},
+		)]);
+		WobFifo {
+			path: PathBuf::new(),
+			styles,
+			sender: async_channel::unbounded().0,
+		}
+	}
+
+	#[test]
+	fn parse_line_scales_the_value() {
+		assert_eq!(
+			wob_fifo().parse_line("42"),
+			Ok(vec![(ArgTypes::CustomProgress, Some("0.42".to_owned()))])
+		);
+		assert_eq!(
+			wob_fifo().parse_line("100"),
+			Ok(vec![(ArgTypes::CustomProgress, Some("1".to_owned()))])
+		);
+	}
+
+	#[test]
+	fn parse_line_applies_the_style() {
+		assert_eq!(
+			wob_fifo().parse_line("50 volume"),
+			Ok(vec![
+				(
+					ArgTypes::CustomIcon,
+					Some("audio-volume-high-symbolic".to_owned())
+				),
+				(ArgTypes::CustomProgressText, Some("Volume".to_owned())),
+				(ArgTypes::CustomProgress, Some("0.5".to_owned())),
+			])
+		);
+	}
+
+	#[test]
+	fn parse_line_ignores_old_wob_colors() {
+		assert_eq!(
+			wob_fifo().parse_line("25 #FFFFFFFF #000000FF #FFFFFFFF"),
+			Ok(vec![(ArgTypes::CustomProgress, Some("0.25".to_owned()))])
+		);
+	}
+
+	#[test]
+	fn parse_line_rejects_invalid_lines() {
+		assert!(wob_fifo().parse_line("loud").is_err());
+		assert!(wob_fifo().parse_line("50 brightness").is_err());
+	}
+}
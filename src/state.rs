@@ -0,0 +1,179 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use serde_derive::{Deserialize, Serialize};
+use zbus::zvariant::Type;
+
+/// The state of a sink/source after a volume action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct VolumeState {
+	pub device: String,
+	pub description: String,
+	pub volume: f64,
+	pub muted: bool,
+	pub max_volume: u8,
+	/// The volume of each channel in percent
+	pub channel_volumes: Vec<f64>,
+}
+
+/// The state of a brightness device after a brightness action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct BrightnessState {
+	pub backend: String,
+	pub device: String,
+	pub value: u32,
+	pub max: u32,
+	pub percent: f64,
+}
+
+/// The state of the media player after a playerctl action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct PlayerState {
+	pub player: String,
+	/// Playing, Paused or Stopped
+	pub status: String,
+	pub icon: String,
+	pub label: String,
+}
+
+/// The state of a lock key after a key lock action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct KeyLockState {
+	/// caps-lock, num-lock or scroll-lock
+	pub key: String,
+	pub state: bool,
+}
+
+/// The displayed progress after a custom progress action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct ProgressState {
+	pub fraction: f64,
+}
+
+/// The displayed progress after a custom segmented progress action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct SegmentedProgressState {
+	pub value: u32,
+	pub n_segments: u32,
+}
+
+/// The inhibit state after an inhibit action
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct InhibitState {
+	pub inhibited: bool,
+	/// The kinds of OSDs that are still displayed while inhibited
+	pub shown_kinds: Vec<String>,
+}
+
+/// Describes an OSD that was displayed by the server.
+/// The device is the sink, source, brightness device or player, empty for the other kinds.
+/// The monitor is empty when the OSD was displayed on all monitors
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default, PartialEq)]
+pub struct OsdEvent {
+	pub kind: String,
+	pub value: f64,
+	pub max: f64,
+	pub muted: bool,
+	pub label: String,
+	pub icon: String,
+	pub device: String,
+	pub monitor: String,
+}
+
+/// Ex: "sink-volume 55/100 muted alsa_output.pci-0000_00_1f.3.analog-stereo eDP-1"
+impl fmt::Display for OsdEvent {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {}/{}", self.kind, self.value, self.max)?;
+		if self.muted {
+			write!(f, " muted")?;
+		}
+		for field in [&self.device, &self.monitor] {
+			if !field.is_empty() {
+				write!(f, " {}", field)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn volume_state_json() {
+		let state = VolumeState {
+			device: "alsa_output.pci-0000_00_1f.3.analog-stereo".to_owned(),
+			description: "Built-in Audio Analog Stereo".to_owned(),
+			volume: 55.0,
+			muted: true,
+			max_volume: 100,
+			channel_volumes: vec![50.0, 60.0],
+		};
+		assert_eq!(
+			serde_json::to_value(&state).unwrap(),
+			json!({
+				"device": "alsa_output.pci-0000_00_1f.3.analog-stereo",
+				"description": "Built-in Audio Analog Stereo",
+				"volume": 55.0,
+				"muted": true,
+				"max_volume": 100,
+				"channel_volumes": [50.0, 60.0],
+			})
+		);
+	}
+
+	#[test]
+	fn brightness_state_json() {
+		let state = BrightnessState {
+			backend: "brightnessctl".to_owned(),
+			device: "intel_backlight".to_owned(),
+			value: 11636,
+			max: 19393,
+			percent: 60.0,
+		};
+		assert_eq!(
+			serde_json::to_value(&state).unwrap(),
+			json!({
+				"backend": "brightnessctl",
+				"device": "intel_backlight",
+				"value": 11636,
+				"max": 19393,
+				"percent": 60.0,
+			})
+		);
+	}
+
+	#[test]
+	fn player_state_json() {
+		let state = PlayerState {
+			player: "spotify".to_owned(),
+			status: "Playing".to_owned(),
+			icon: "media-playback-start-symbolic".to_owned(),
+			label: "Artist - Title".to_owned(),
+		};
+		assert_eq!(
+			serde_json::to_value(&state).unwrap(),
+			json!({
+				"player": "spotify",
+				"status": "Playing",
+				"icon": "media-playback-start-symbolic",
+				"label": "Artist - Title",
+			})
+		);
+	}
+
+	#[test]
+	fn states_round_trip_through_json() {
+		let state = VolumeState {
+			device: "alsa_input".to_owned(),
+			volume: 12.0,
+			max_volume: 150,
+			..Default::default()
+		};
+		let json = serde_json::to_string(&state).unwrap();
+		assert_eq!(serde_json::from_str::<VolumeState>(&json).unwrap(), state);
+	}
+}
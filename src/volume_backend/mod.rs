@@ -0,0 +1,105 @@
+use self::{pipewire::PipeWire, pulse::Pulse};
+
+use crate::config::user::AudioBackend;
+use crate::error::ActionError;
+use crate::utils::VolumeDeviceType;
+
+mod pipewire;
+
+mod pulse;
+
+pub type VolumeBackendResult = Result<Box<dyn VolumeBackend>, ActionError>;
+
+pub trait VolumeBackendConstructor: VolumeBackend + Sized + 'static {
+	fn try_new(device_type: VolumeDeviceType) -> Result<Self, ActionError>;
+
+	fn try_new_boxed(device_type: VolumeDeviceType) -> VolumeBackendResult {
+		let backend = Self::try_new(device_type);
+		match backend {
+			Ok(backend) => Ok(Box::new(backend)),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+/// A sink or source as reported by the audio server
+#[derive(Clone, Debug, Default)]
+pub struct VolumeDevice {
+	/// The PulseAudio device index or the PipeWire node ID
+	pub index: u32,
+	pub name: String,
+	pub description: String,
+	/// The volume of each channel in percent
+	pub channel_volumes: Vec<f64>,
+	pub muted: bool,
+}
+
+impl VolumeDevice {
+	/// The average volume of all channels in percent
+	pub fn volume(&self) -> f64 {
+		if self.channel_volumes.is_empty() {
+			return 0.0;
+		}
+		let sum: f64 = self.channel_volumes.iter().sum();
+		(sum / self.channel_volumes.len() as f64).round()
+	}
+
+	/// The volume of the loudest channel in percent
+	pub fn max_channel_volume(&self) -> f64 {
+		self.channel_volumes.iter().copied().fold(0.0, f64::max)
+	}
+}
+
+#[allow(unused)]
+pub trait VolumeBackend {
+	fn get_backend_name(&self) -> &'static str;
+	fn get_device_type(&self) -> VolumeDeviceType;
+
+	/// Gets the default device when no name is given
+	fn get_device(&mut self, device_name: Option<&str>) -> Result<VolumeDevice, ActionError>;
+	fn get_device_by_index(&mut self, index: u32) -> Result<VolumeDevice, ActionError>;
+
+	/// Sets the loudest channel to the volume in percent
+	fn set_volume(&mut self, device: &VolumeDevice, volume: f64) -> Result<(), ActionError>;
+	fn set_mute(&mut self, device: &VolumeDevice, mute: bool) -> Result<(), ActionError>;
+}
+
+/// Whether the backend has to be recreated after the error, because the connection to the
+/// audio server broke. Missing devices and invalid values leave the backend usable
+#[allow(dead_code)]
+pub fn needs_reconnect(error: &ActionError) -> bool {
+	matches!(error, ActionError::Failed(_) | ActionError::ZBus(_))
+}
+
+#[allow(dead_code)]
+pub fn get_preferred_backend(
+	device_type: VolumeDeviceType,
+	preference: AudioBackend,
+) -> VolumeBackendResult {
+	match preference {
+		AudioBackend::Pulse => Pulse::try_new_boxed(device_type),
+		AudioBackend::PipeWire => PipeWire::try_new_boxed(device_type),
+		AudioBackend::Auto => Pulse::try_new_boxed(device_type).or_else(|error| {
+			eprintln!("{}", error);
+			eprintln!("Trying PipeWire Backend...");
+			PipeWire::try_new_boxed(device_type)
+		}),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn needs_reconnect_only_after_connection_errors() {
+		assert!(needs_reconnect(&ActionError::Failed(
+			"Pulse Error".to_owned()
+		)));
+		assert!(needs_reconnect(&ActionError::ZBus(zbus::Error::Failure(
+			"Disconnected".to_owned()
+		))));
+		assert!(!needs_reconnect(&ActionError::NoSuchDevice(String::new())));
+		assert!(!needs_reconnect(&ActionError::InvalidValue(String::new())));
+	}
+}
@@ -0,0 +1,526 @@
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+use crate::error::ActionError;
+use crate::utils::VolumeDeviceType;
+
+use super::{VolumeBackend, VolumeBackendConstructor, VolumeDevice};
+
+/// Talks to PipeWire without pipewire-pulse. Needs the `pw-dump` and `pw-cli` tools at
+/// runtime: the nodes and the default metadata are read with `pw-dump` once per action.
+/// The volumes are written with `pw-cli` to the Route param of the device that owns the
+/// node, like WirePlumber does for ALSA cards, or to the Props of nodes without a device
+pub(super) struct PipeWire {
+	device_type: VolumeDeviceType,
+	/// The objects from the last pw-dump
+	objects: Vec<Value>,
+	/// The device with the values of the last write. PipeWire applies them asynchronously,
+	/// so a pw-dump right after the write could still show the old values
+	written: Option<VolumeDevice>,
+}
+
+/// Where the volume of a node is written
+#[derive(Debug, PartialEq)]
+enum Target {
+	/// The active route of the device that owns the node
+	Route {
+		device_id: u64,
+		index: u64,
+		device: u64,
+	},
+	/// The Props param of the node
+	Node(u32),
+}
+
+impl VolumeBackendConstructor for PipeWire {
+	fn try_new(device_type: VolumeDeviceType) -> Result<Self, ActionError> {
+		Ok(Self {
+			device_type,
+			objects: pw_dump()?,
+			written: None,
+		})
+	}
+}
+
+impl PipeWire {
+	/// Reads the current nodes and metadata
+	fn refresh(&mut self) -> Result<(), ActionError> {
+		self.objects = pw_dump()?;
+		self.written = None;
+		Ok(())
+	}
+
+	fn media_class_matches(&self, media_class: &str) -> bool {
+		match self.device_type {
+			VolumeDeviceType::Sink => media_class == "Audio/Sink",
+			VolumeDeviceType::Source => media_class.starts_with("Audio/Source"),
+		}
+	}
+
+	fn nodes(&self) -> impl Iterator<Item = &Value> {
+		self.objects.iter().filter(|object| {
+			object["type"] == "PipeWire:Interface:Node"
+				&& object["info"]["props"]["media.class"]
+					.as_str()
+					.is_some_and(|class| self.media_class_matches(class))
+		})
+	}
+
+	/// The node name set in the "default" metadata
+	fn default_node_name(&self) -> Option<String> {
+		let key = match self.device_type {
+			VolumeDeviceType::Sink => "default.audio.sink",
+			VolumeDeviceType::Source => "default.audio.source",
+		};
+		let metadata = self.objects.iter().find(|object| {
+			object["type"] == "PipeWire:Interface:Metadata"
+				&& object["props"]["metadata.name"] == "default"
+		})?;
+		let entry = metadata["metadata"]
+			.as_array()?
+			.iter()
+			.find(|entry| entry["key"] == key)?;
+		// Older versions of pw-dump don't parse the JSON value
+		let value = match &entry["value"] {
+			Value::String(json) => serde_json::from_str(json).ok()?,
+			value => value.clone(),
+		};
+		value["name"].as_str().map(str::to_owned)
+	}
+
+	/// The route of the node's device in the same direction, or the node itself when the
+	/// node has no device or the device has no active route for it
+	fn target(&self, id: u32) -> Target {
+		let route = || {
+			let node = self.nodes().find(|node| node["id"] == id)?;
+			let props = &node["info"]["props"];
+			let device_id = props["device.id"].as_u64()?;
+			let route_device = props["card.profile.device"].as_u64()?;
+			let direction = match self.device_type {
+				VolumeDeviceType::Sink => "Output",
+				VolumeDeviceType::Source => "Input",
+			};
+			let device = self.objects.iter().find(|object| {
+				object["type"] == "PipeWire:Interface:Device" && object["id"] == device_id
+			})?;
+			let route = device["info"]["params"]["Route"]
+				.as_array()?
+				.iter()
+				.find(|route| route["device"] == route_device && route["direction"] == direction)?;
+			Some(Target::Route {
+				device_id,
+				index: route["index"].as_u64()?,
+				device: route_device,
+			})
+		};
+		route().unwrap_or(Target::Node(id))
+	}
+
+	/// Writes the props of the node and keeps the written device for the next read
+	fn write(&mut self, written: VolumeDevice, props: Value) -> Result<(), ActionError> {
+		match self.target(written.index) {
+			Target::Route {
+				device_id,
+				index,
+				device,
+			} => set_param(
+				device_id,
+				"Route",
+				json!({ "index": index, "device": device, "props": props, "save": true }),
+			)?,
+			Target::Node(id) => set_param(id as u64, "Props", props)?,
+		}
+		self.written = Some(written);
+		Ok(())
+	}
+}
+
+impl VolumeBackend for PipeWire {
+	fn get_backend_name(&self) -> &'static str {
+		"pipewire"
+	}
+
+	fn get_device_type(&self) -> VolumeDeviceType {
+		self.device_type
+	}
+
+	fn get_device(&mut self, device_name: Option<&str>) -> Result<VolumeDevice, ActionError> {
+		self.refresh()?;
+		let name = match device_name {
+			Some(name) => name.to_owned(),
+			None => self.default_node_name().ok_or_else(|| {
+				ActionError::NoSuchDevice("No default device in the PipeWire metadata".to_owned())
+			})?,
+		};
+		self.nodes()
+			.find(|node| node["info"]["props"]["node.name"] == name.as_str())
+			.map(volume_device)
+			.ok_or_else(|| ActionError::NoSuchDevice(format!("No device named \"{}\"", name)))
+	}
+
+	fn get_device_by_index(&mut self, index: u32) -> Result<VolumeDevice, ActionError> {
+		if let Some(device) = self.written.take_if(|device| device.index == index) {
+			return Ok(device);
+		}
+		self.refresh()?;
+		self.nodes()
+			.find(|node| node["id"] == index)
+			.map(volume_device)
+			.ok_or_else(|| ActionError::NoSuchDevice(format!("No node with the ID {}", index)))
+	}
+
+	fn set_volume(&mut self, device: &VolumeDevice, volume: f64) -> Result<(), ActionError> {
+		if device.channel_volumes.is_empty() {
+			return Err(pipewire_error(format!(
+				"The node {} has no channel volumes",
+				device.index
+			)));
+		}
+		// Scale the channels from the current volumes to keep their balance
+		let channel_volumes = scale_channel_volumes(&device.channel_volumes, volume);
+		let linear: Vec<f64> = channel_volumes
+			.iter()
+			.copied()
+			.map(volume_to_linear)
+			.collect();
+		self.write(
+			VolumeDevice {
+				channel_volumes,
+				..device.clone()
+			},
+			json!({ "channelVolumes": linear }),
+		)
+	}
+
+	fn set_mute(&mut self, device: &VolumeDevice, mute: bool) -> Result<(), ActionError> {
+		self.write(
+			VolumeDevice {
+				muted: mute,
+				..device.clone()
+			},
+			json!({ "mute": mute }),
+		)
+	}
+}
+
+fn pipewire_error(message: impl std::fmt::Display) -> ActionError {
+	ActionError::Failed(format!("PipeWire Error: {}", message))
+}
+
+fn run(program: &str, args: &[&str]) -> Result<Vec<u8>, ActionError> {
+	let output = Command::new(program)
+		.args(args)
+		.output()
+		.map_err(|e| pipewire_error(format!("Could not run {}: {}", program, e)))?;
+	if !output.status.success() {
+		return Err(pipewire_error(format!(
+			"{} failed: {}",
+			program,
+			String::from_utf8_lossy(&output.stderr).trim()
+		)));
+	}
+	Ok(output.stdout)
+}
+
+fn pw_dump() -> Result<Vec<Value>, ActionError> {
+	serde_json::from_slice(&run("pw-dump", &[])?)
+		.map_err(|e| pipewire_error(format!("Could not parse the output of pw-dump: {}", e)))
+}
+
+/// Writes the param of the node or device
+fn set_param(id: u64, param: &str, value: Value) -> Result<(), ActionError> {
+	run(
+		"pw-cli",
+		&["set-param", &id.to_string(), param, &value.to_string()],
+	)
+	.map(|_| ())
+}
+
+/// Scales the channels so the loudest one has the volume, like pa_cvolume_scale
+fn scale_channel_volumes(channel_volumes: &[f64], volume: f64) -> Vec<f64> {
+	let volume = volume.max(0.0);
+	let max = channel_volumes.iter().copied().fold(0.0, f64::max);
+	channel_volumes
+		.iter()
+		.map(|channel| {
+			if max > 0.0 {
+				channel * volume / max
+			} else {
+				volume
+			}
+		})
+		.collect()
+}
+
+/// The linear volume of the node from the volume in percent on the cubic scale
+fn volume_to_linear(volume: f64) -> f64 {
+	(volume / 100.0).powi(3)
+}
+
+fn volume_device(node: &Value) -> VolumeDevice {
+	let props = &node["info"]["props"];
+	let name = props["node.name"].as_str().unwrap_or_default();
+	let description = props["node.description"]
+		.as_str()
+		.or(props["node.nick"].as_str())
+		.unwrap_or(name);
+	// Only one of the Props params holds the volumes, the others are driver specific
+	let params = node["info"]["params"]["Props"]
+		.as_array()
+		.and_then(|params| {
+			params
+				.iter()
+				.find(|param| param["channelVolumes"].is_array())
+		});
+	let (channel_volumes, muted) = match params {
+		Some(param) => (
+			param["channelVolumes"]
+				.as_array()
+				.into_iter()
+				.flatten()
+				.filter_map(Value::as_f64)
+				// The volumes are linear, but shown on the same cubic scale as PulseAudio
+				.map(|volume| volume.cbrt() * 100.0)
+				.collect(),
+			param["mute"].as_bool().unwrap_or(false),
+		),
+		None => (Vec::new(), false),
+	};
+	VolumeDevice {
+		index: node["id"].as_u64().unwrap_or_default() as u32,
+		name: name.to_owned(),
+		description: description.to_owned(),
+		channel_volumes,
+		muted,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn node(id: u32, media_class: &str, name: &str, props: Value) -> Value {
+		json!({
+			"id": id,
+			"type": "PipeWire:Interface:Node",
+			"info": {
+				"props": {
+					"media.class": media_class,
+					"node.name": name,
+				},
+				"params": { "Props": [{ "volume": 1.0 }, props] },
+			},
+		})
+	}
+
+	fn default_metadata(key: &str, value: Value) -> Value {
+		json!({
+			"id": 40,
+			"type": "PipeWire:Interface:Metadata",
+			"props": { "metadata.name": "default" },
+			"metadata": [{ "subject": 0, "key": key, "type": "Spa:String:JSON", "value": value }],
+		})
+	}
+
+	#[test]
+	fn volume_device_reads_the_cubic_channel_volumes() {
+		let mut node = node(
+			52,
+			"Audio/Sink",
+			"alsa_output.pci-0000_00_1f.3.analog-stereo",
+			json!({ "channelVolumes": [0.125, 0.216], "mute": true }),
+		);
+		node["info"]["props"]["node.description"] = json!("Built-in Audio Analog Stereo");
+		let device = volume_device(&node);
+		assert_eq!(device.index, 52);
+		assert_eq!(device.name, "alsa_output.pci-0000_00_1f.3.analog-stereo");
+		assert_eq!(device.description, "Built-in Audio Analog Stereo");
+		assert_eq!(device.channel_volumes.len(), 2);
+		assert!((device.channel_volumes[0] - 50.0).abs() < 1e-9);
+		assert!((device.channel_volumes[1] - 60.0).abs() < 1e-9);
+		assert!(device.muted);
+		assert!((volume_to_linear(device.channel_volumes[0]) - 0.125).abs() < 1e-9);
+	}
+
+	#[test]
+	fn volume_device_falls_back_to_the_nick_and_name() {
+		let mut with_nick = node(1, "Audio/Sink", "sink", json!({ "channelVolumes": [1.0] }));
+		with_nick["info"]["props"]["node.nick"] = json!("Speakers");
+		assert_eq!(volume_device(&with_nick).description, "Speakers");
+
+		let without_props = node(2, "Audio/Sink", "sink", json!({}));
+		let device = volume_device(&without_props);
+		assert_eq!(device.description, "sink");
+		assert!(device.channel_volumes.is_empty());
+		assert!(!device.muted);
+	}
+
+	#[test]
+	fn default_node_name_reads_the_metadata() {
+		let backend = PipeWire {
+			device_type: VolumeDeviceType::Sink,
+			objects: vec![default_metadata(
+				"default.audio.sink",
+				json!({ "name": "alsa_output.usb" }),
+			)],
+			written: None,
+		};
+		assert_eq!(
+			backend.default_node_name().as_deref(),
+			Some("alsa_output.usb")
+		);
+
+		// Older versions of pw-dump keep the JSON value as a string
+		let backend = PipeWire {
+			device_type: VolumeDeviceType::Source,
+			objects: vec![default_metadata(
+				"default.audio.source",
+				json!("{ \"name\": \"alsa_input.usb\" }"),
+			)],
+			written: None,
+		};
+		assert_eq!(
+			backend.default_node_name().as_deref(),
+			Some("alsa_input.usb")
+		);
+
+		let backend = PipeWire {
+			device_type: VolumeDeviceType::Source,
+			objects: vec![default_metadata(
+				"default.audio.sink",
+				json!({ "name": "alsa_output.usb" }),
+			)],
+			written: None,
+		};
+		assert_eq!(backend.default_node_name(), None);
+	}
+
+	#[test]
+	fn nodes_match_the_device_type() {
+		let objects = vec![
+			node(1, "Audio/Sink", "sink", json!({})),
+			node(2, "Audio/Source", "source", json!({})),
+			node(3, "Audio/Source/Virtual", "virtual-source", json!({})),
+			node(4, "Stream/Output/Audio", "stream", json!({})),
+		];
+		let names = |device_type| {
+			let backend = PipeWire {
+				device_type,
+				objects: objects.clone(),
+				written: None,
+			};
+			backend
+				.nodes()
+				.map(|node| {
+					node["info"]["props"]["node.name"]
+						.as_str()
+						.unwrap()
+						.to_owned()
+				})
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(names(VolumeDeviceType::Sink), ["sink"]);
+		assert_eq!(
+			names(VolumeDeviceType::Source),
+			["source", "virtual-source"]
+		);
+	}
+
+	#[test]
+	fn scale_channel_volumes_keeps_the_balance() {
+		assert_eq!(scale_channel_volumes(&[40.0, 20.0], 60.0), [60.0, 30.0]);
+		assert_eq!(scale_channel_volumes(&[0.0, 0.0], 25.0), [25.0, 25.0]);
+		assert_eq!(scale_channel_volumes(&[50.0], -5.0), [0.0]);
+	}
+
+	fn alsa_objects() -> Vec<Value> {
+		let mut sink = node(
+			52,
+			"Audio/Sink",
+			"alsa_output",
+			json!({ "channelVolumes": [1.0] }),
+		);
+		sink["info"]["props"]["device.id"] = json!(45);
+		sink["info"]["props"]["card.profile.device"] = json!(3);
+		let device = json!({
+			"id": 45,
+			"type": "PipeWire:Interface:Device",
+			"info": {
+				"params": {
+					"Route": [
+						{ "index": 1, "direction": "Input", "device": 4 },
+						{ "index": 2, "direction": "Output", "device": 3 },
+					],
+				},
+			},
+		});
+		vec![
+			sink,
+			device,
+			node(60, "Audio/Sink", "virtual-sink", json!({})),
+		]
+	}
+
+	#[test]
+	fn target_is_the_route_of_the_device() {
+		let backend = PipeWire {
+			device_type: VolumeDeviceType::Sink,
+			objects: alsa_objects(),
+			written: None,
+		};
+		assert_eq!(
+			backend.target(52),
+			Target::Route {
+				device_id: 45,
+				index: 2,
+				device: 3,
+			}
+		);
+		// Nodes without a device keep their volume in their own Props
+		assert_eq!(backend.target(60), Target::Node(60));
+
+		let mut objects = alsa_objects();
+		objects[1]["info"]["params"]["Route"][1]["direction"] = json!("Input");
+		let backend = PipeWire {
+			device_type: VolumeDeviceType::Sink,
+			objects,
+			written: None,
+		};
+		assert_eq!(backend.target(52), Target::Node(52));
+	}
+
+	#[test]
+	fn get_device_by_index_returns_the_written_device() {
+		let written = VolumeDevice {
+			index: 52,
+			channel_volumes: vec![60.0, 30.0],
+			..Default::default()
+		};
+		let mut backend = PipeWire {
+			device_type: VolumeDeviceType::Sink,
+			objects: Vec::new(),
+			written: Some(written),
+		};
+		let device = backend.get_device_by_index(52).unwrap();
+		assert_eq!(device.channel_volumes, [60.0, 30.0]);
+		assert!(backend.written.is_none());
+	}
+
+	#[test]
+	fn set_volume_needs_channel_volumes() {
+		let mut backend = PipeWire {
+			device_type: VolumeDeviceType::Sink,
+			objects: Vec::new(),
+			written: None,
+		};
+		let device = VolumeDevice {
+			index: 52,
+			..Default::default()
+		};
+		assert!(matches!(
+			backend.set_volume(&device, 50.0),
+			Err(ActionError::Failed(_))
+		));
+	}
+}
@@ -0,0 +1,134 @@
+use pulse::volume::Volume;
+use pulsectl::{
+	controllers::{types::DeviceInfo, DeviceControl, SinkController, SourceController},
+	ControllerError,
+};
+
+use crate::error::ActionError;
+use crate::utils::VolumeDeviceType;
+
+use super::{VolumeBackend, VolumeBackendConstructor, VolumeDevice};
+
+/// Talks to PulseAudio, or to PipeWire through pipewire-pulse
+pub(super) struct Pulse {
+	device_type: VolumeDeviceType,
+	controller: Box<dyn DeviceControl<DeviceInfo>>,
+}
+
+impl VolumeBackendConstructor for Pulse {
+	fn try_new(device_type: VolumeDeviceType) -> Result<Self, ActionError> {
+		let controller: Box<dyn DeviceControl<DeviceInfo>> = match device_type {
+			VolumeDeviceType::Sink => Box::new(SinkController::create().map_err(pulse_error)?),
+			VolumeDeviceType::Source => Box::new(SourceController::create().map_err(pulse_error)?),
+		};
+		Ok(Self {
+			device_type,
+			controller,
+		})
+	}
+}
+
+impl VolumeBackend for Pulse {
+	fn get_backend_name(&self) -> &'static str {
+		"pulse"
+	}
+
+	fn get_device_type(&self) -> VolumeDeviceType {
+		self.device_type
+	}
+
+	fn get_device(&mut self, device_name: Option<&str>) -> Result<VolumeDevice, ActionError> {
+		let device = match device_name {
+			Some(name) => self
+				.controller
+				.get_device_by_name(name)
+				.map_err(|e| device_error(e, |_| format!("No device named \"{}\"", name)))?,
+			None => self
+				.controller
+				.get_default_device()
+				.map_err(|e| device_error(e, |e| format!("No default device: {}", e)))?,
+		};
+		Ok(volume_device(&device))
+	}
+
+	fn get_device_by_index(&mut self, index: u32) -> Result<VolumeDevice, ActionError> {
+		let device = self
+			.controller
+			.get_device_by_index(index)
+			.map_err(pulse_error)?;
+		Ok(volume_device(&device))
+	}
+
+	fn set_volume(&mut self, device: &VolumeDevice, volume: f64) -> Result<(), ActionError> {
+		// Scale the channels from the current volumes to keep their balance
+		let mut info = self
+			.controller
+			.get_device_by_index(device.index)
+			.map_err(pulse_error)?;
+		if let Some(volumes) = info.volume.scale(volume_from_f64(volume)) {
+			self.controller
+				.set_device_volume_by_index(device.index, volumes);
+		}
+		Ok(())
+	}
+
+	fn set_mute(&mut self, device: &VolumeDevice, mute: bool) -> Result<(), ActionError> {
+		self.controller.set_device_mute_by_index(device.index, mute);
+		Ok(())
+	}
+}
+
+fn pulse_error(error: ControllerError) -> ActionError {
+	ActionError::Failed(format!("Pulse Error: {}", error))
+}
+
+/// Only a failed lookup means that the device doesn't exist. The other errors come
+/// from the connection, which has to be recreated
+fn device_error(
+	error: ControllerError,
+	message: impl FnOnce(&ControllerError) -> String,
+) -> ActionError {
+	match error {
+		ControllerError::GetInfo(_) => ActionError::NoSuchDevice(message(&error)),
+		error => pulse_error(error),
+	}
+}
+
+fn volume_to_f64(volume: &Volume) -> f64 {
+	let tmp_vol = f64::from(volume.0 - Volume::MUTED.0);
+	100.0 * tmp_vol / f64::from(Volume::NORMAL.0 - Volume::MUTED.0)
+}
+
+fn volume_from_f64(volume: f64) -> Volume {
+	let tmp = f64::from(Volume::NORMAL.0 - Volume::MUTED.0) * volume.max(0.0) / 100_f64;
+	Volume((tmp + f64::from(Volume::MUTED.0)).round() as u32)
+}
+
+fn volume_device(device: &DeviceInfo) -> VolumeDevice {
+	VolumeDevice {
+		index: device.index,
+		name: device.name.clone().unwrap_or_default(),
+		description: device.description.clone().unwrap_or_default(),
+		channel_volumes: device.volume.get().iter().map(volume_to_f64).collect(),
+		muted: device.mute,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn device_error_keeps_lookup_misses_apart_from_connection_errors() {
+		let error = device_error(ControllerError::GetInfo("Error".to_owned()), |_| {
+			"No device named \"hdmi\"".to_owned()
+		});
+		assert!(matches!(error, ActionError::NoSuchDevice(message) if message.contains("hdmi")));
+
+		let error = device_error(
+			ControllerError::PulseCtl("Connection terminated".to_owned()),
+			|_| unreachable!(),
+		);
+		assert!(matches!(error, ActionError::Failed(_)));
+	}
+}